@@ -0,0 +1,80 @@
+#[macro_use]
+extern crate criterion;
+extern crate conway_rs;
+
+use criterion::{BatchSize, BenchmarkId, Criterion, Throughput};
+
+use conway_rs::{patterns, Map, Pattern, Ruleset, DEFAULT_RULE};
+
+fn seeded_map(size: usize) -> Map {
+    let mut map = Map::new(size, size, Ruleset::parse(DEFAULT_RULE).unwrap());
+    map.init_noise_seeded(1);
+    map
+}
+
+// Half the cells alive at random
+fn dense_map(size: usize) -> Map {
+    let mut map = Map::new(size, size, Ruleset::parse(DEFAULT_RULE).unwrap());
+    map.init_random(0.5, 1);
+    map
+}
+
+// A glider every GLIDER_SPACING cells each way and nothing else
+const GLIDER_SPACING: usize = 25;
+
+fn glider_field(size: usize) -> Map {
+    let mut map = Map::new(size, size, Ruleset::parse(DEFAULT_RULE).unwrap());
+    let glider = Pattern::from_rle(patterns::GLIDER).unwrap();
+    for y in (0..size - glider.height).step_by(GLIDER_SPACING) {
+        for x in (0..size - glider.width).step_by(GLIDER_SPACING) {
+            map.stamp(&glider, x, y).unwrap();
+        }
+    }
+    map
+}
+
+// Builds a size x size board to benchmark
+type Board = fn(usize) -> Map;
+
+// Generations per second on boards of several sizes, dense and sparse.  Each
+// measurement ticks a fresh copy of the same starting board, so the boards
+// don't settle down as the benchmark runs.
+fn tick_throughput(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tick");
+    group.throughput(Throughput::Elements(1));
+    for &size in &[100, 300, 1000] {
+        let cases: [(&str, Board); 2] = [("dense", dense_map), ("gliders", glider_field)];
+        for &(name, build) in &cases {
+            let map = build(size);
+            group.bench_with_input(BenchmarkId::new(name, size), &map, |b, map| {
+                b.iter_batched(|| map.clone(), |mut map| map.tick(), BatchSize::LargeInput)
+            });
+        }
+    }
+    group.finish();
+}
+
+fn serial_tick(c: &mut Criterion) {
+    let mut map = seeded_map(500);
+    c.bench_function("tick 500x500 serial", move |b| b.iter(|| map.tick()));
+}
+
+// Every extra step of radius adds a ring of cells to each neighbour count
+fn radius_tick(c: &mut Criterion) {
+    let mut map = seeded_map(500);
+    map.radius = 3;
+    c.bench_function("tick 500x500 radius 3", move |b| b.iter(|| map.tick()));
+}
+
+#[cfg(feature = "rayon")]
+fn parallel_tick(c: &mut Criterion) {
+    let mut map = seeded_map(500);
+    map.parallel = true;
+    c.bench_function("tick 500x500 parallel", move |b| b.iter(|| map.tick()));
+}
+
+#[cfg(not(feature = "rayon"))]
+criterion_group!(benches, tick_throughput, serial_tick, radius_tick);
+#[cfg(feature = "rayon")]
+criterion_group!(benches, tick_throughput, serial_tick, radius_tick, parallel_tick);
+criterion_main!(benches);
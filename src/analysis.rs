@@ -0,0 +1,98 @@
+use map::Map;
+
+// Generations a glider takes to move one cell diagonally
+pub const GLIDER_PERIOD: u64 = 4;
+
+impl Map {
+    // Count the gliders that have moved since `prev`, which should be this
+    // board GLIDER_PERIOD generations earlier: groups of touching live cells
+    // that reappear with the same shape, shifted one cell diagonally.  Each
+    // group in `prev` accounts for at most one glider.  Groups aren't joined
+    // across edges that wrap.
+    pub fn detect_spaceships(&self, prev: &Map) -> usize {
+        let mut before = prev.components();
+        let mut count = 0;
+        for shape in self.components() {
+            let moved = before.iter().position(|old| {
+                let (dx, dy) = (shape[0].0 as i32 - old[0].0 as i32, shape[0].1 as i32 - old[0].1 as i32);
+                dx.abs() == 1 && dy.abs() == 1 && old.len() == shape.len()
+                    && old.iter().zip(&shape)
+                        .all(|(&(ox, oy), &(x, y))| x as i32 - ox as i32 == dx && y as i32 - oy as i32 == dy)
+            });
+            if let Some(i) = moved {
+                before.swap_remove(i);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    // The live cells split into groups that touch, including diagonally.
+    // Each group's cells are in row-major order.
+    fn components(&self) -> Vec<Vec<(usize, usize)>> {
+        let mut seen = vec![false; self.width * self.height];
+        let mut groups = Vec::new();
+        for (x, y) in self.live_iter() {
+            if seen[self.idx(x, y)] { continue };
+            seen[self.idx(x, y)] = true;
+            let mut group = Vec::new();
+            let mut stack = vec![(x, y)];
+            while let Some((i, j)) = stack.pop() {
+                group.push((i, j));
+                for nj in j.saturating_sub(1)..(j + 2).min(self.height) {
+                    for ni in i.saturating_sub(1)..(i + 2).min(self.width) {
+                        let n = self.idx(ni, nj);
+                        if !seen[n] && self.front[n].alive {
+                            seen[n] = true;
+                            stack.push((ni, nj));
+                        }
+                    }
+                }
+            }
+            group.sort_by_key(|&(i, j)| (j, i));
+            groups.push(group);
+        }
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use map::Map;
+    use pattern::Orientation;
+    use patterns::{GLIDER, LWSS};
+    use rules::{Ruleset, DEFAULT_RULE};
+
+    fn conway() -> Map {
+        Map::new(60, 60, Ruleset::parse(DEFAULT_RULE).unwrap())
+    }
+
+    // The board after a glider's period, alongside the board before it
+    fn after_a_period(map: &Map) -> Map {
+        let mut later = map.clone();
+        for _ in 0..super::GLIDER_PERIOD {
+            later.tick();
+        }
+        later
+    }
+
+    #[test]
+    fn counts_gliders_heading_every_way() {
+        let mut map = conway();
+        map.load_rle(GLIDER, 10, 10, Orientation::R0).unwrap();
+        map.load_rle(GLIDER, 40, 10, Orientation::R90).unwrap();
+        map.load_rle(GLIDER, 10, 40, Orientation::R180).unwrap();
+        map.load_rle(GLIDER, 40, 40, Orientation::R270).unwrap();
+        assert_eq!(after_a_period(&map).detect_spaceships(&map), 4);
+    }
+
+    #[test]
+    fn ignores_still_lifes_and_other_ships() {
+        let mut map = conway();
+        // A block, a blinker and a lightweight spaceship
+        map.load_rle("x = 2, y = 2\n2o$2o!", 5, 5, Orientation::R0).unwrap();
+        map.load_rle("x = 3, y = 1\n3o!", 20, 5, Orientation::R0).unwrap();
+        map.load_rle(LWSS, 30, 30, Orientation::R0).unwrap();
+        assert_eq!(after_a_period(&map).detect_spaceships(&map), 0);
+    }
+}
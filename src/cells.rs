@@ -0,0 +1,73 @@
+use map::Map;
+use pattern::Pattern;
+
+impl Pattern {
+    // Parse a pattern in the plaintext `.cells` format: `!` comment lines,
+    // then one line per row with `O` or `*` for live cells and `.` or a space
+    // for dead ones.  Short lines are padded out with dead cells.
+    pub fn from_cells(text: &str) -> Result<Pattern, String> {
+        let mut rows: Vec<&str> = text.lines()
+            .map(|l| l.trim_end_matches('\r'))
+            .filter(|l| !l.starts_with('!'))
+            .collect();
+        while rows.last().is_some_and(|r| r.trim().is_empty()) {
+            rows.pop();
+        }
+
+        let mut cells = Vec::new();
+        let mut width = 0;
+        for (y, row) in rows.iter().enumerate() {
+            width = width.max(row.chars().count());
+            for (x, c) in row.chars().enumerate() {
+                match c {
+                    'O' | '*' => cells.push((x, y)),
+                    '.' | ' ' => {},
+                    _ => return Err(format!("unexpected '{}' on line {} of pattern", c, y + 1))
+                }
+            }
+        }
+        Ok(Pattern { width, height: rows.len(), cells })
+    }
+}
+
+impl Map {
+    // Load a plaintext pattern with its top-left corner at the given origin
+    pub fn load_cells(&mut self, text: &str, origin_x: usize, origin_y: usize) -> Result<(), String> {
+        let pattern = Pattern::from_cells(text)?;
+        self.stamp(&pattern, origin_x, origin_y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use map::{Map, MAP_HEIGHT, MAP_WIDTH};
+    use rules::{Ruleset, DEFAULT_RULE};
+
+    fn conway() -> Map {
+        Map::new(MAP_WIDTH, MAP_HEIGHT, Ruleset::parse(DEFAULT_RULE).unwrap())
+    }
+
+    #[test]
+    fn loads_glider() {
+        let mut map = conway();
+        map.load_cells("!Name: Glider\n.O\n..O\nOOO\n", 20, 30).unwrap();
+        assert_eq!(map.live_cells(), 5);
+        for &(x, y) in &[(21, 30), (22, 31), (20, 32), (21, 32), (22, 32)] {
+            assert!(map.cell(x, y).alive);
+        }
+    }
+
+    #[test]
+    fn pads_short_lines() {
+        let mut map = conway();
+        map.load_cells("O\n...O\n*", 0, 0).unwrap();
+        assert_eq!(map.live_cells(), 3);
+        assert!(map.cell(0, 0).alive && map.cell(3, 1).alive && map.cell(0, 2).alive);
+    }
+
+    #[test]
+    fn rejects_unknown_characters() {
+        let mut map = conway();
+        assert!(map.load_cells(".O\nxO", 0, 0).is_err());
+    }
+}
@@ -0,0 +1,71 @@
+// The command bar opened with ':'.  Each line is a command name followed by
+// its argument, e.g. "rule B36/S23" or "goto 500".
+
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use conway_rs::Ruleset;
+
+pub const COMMANDS: &str = "rule, seed, goto or save";
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    // Switch to a rule, in any notation --rule accepts
+    Rule(Ruleset),
+    // Reseed the board the way --init does, from this seed
+    Seed(u32),
+    // Run or rewind to a generation
+    Goto(u64),
+    // Save the board as RLE
+    Save(PathBuf)
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let line = line.trim();
+        let (name, arg) = match line.find(' ') {
+            Some(i) => (&line[..i], line[i..].trim()),
+            None => (line, "")
+        };
+        if name.is_empty() {
+            return Err(format!("expected a command: {}", COMMANDS));
+        }
+        if arg.is_empty() {
+            return Err(format!(":{} needs a value", name));
+        }
+        match name {
+            "rule" => Ok(Command::Rule(Ruleset::parse(arg)?)),
+            "seed" => Ok(Command::Seed(number(name, arg)?)),
+            "goto" => Ok(Command::Goto(number(name, arg)?)),
+            "save" => Ok(Command::Save(PathBuf::from(arg))),
+            _ => Err(format!("unknown command '{}' (expected {})", name, COMMANDS))
+        }
+    }
+}
+
+fn number<T: FromStr>(name: &str, arg: &str) -> Result<T, String> {
+    arg.parse().map_err(|_| format!(":{} expects a number, got '{}'", name, arg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_each_command() {
+        assert_eq!(Command::parse("rule B36/S23"), Ok(Command::Rule(Ruleset::parse("B36/S23").unwrap())));
+        assert_eq!(Command::parse("  seed 42 "), Ok(Command::Seed(42)));
+        assert_eq!(Command::parse("goto 500"), Ok(Command::Goto(500)));
+        // File names may have spaces in them
+        assert_eq!(Command::parse("save my glider.rle"), Ok(Command::Save(PathBuf::from("my glider.rle"))));
+    }
+
+    #[test]
+    fn rejects_bad_commands() {
+        assert!(Command::parse("").is_err());
+        assert!(Command::parse("jump 5").is_err());
+        assert!(Command::parse("goto").is_err());
+        assert!(Command::parse("goto soon").is_err());
+        assert!(Command::parse("rule B9").is_err());
+    }
+}
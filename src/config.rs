@@ -0,0 +1,73 @@
+// Startup defaults read from conway.toml.  Every setting is optional, and
+// command line flags override whatever the file says.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use conway_rs::{DEFAULT_RULE, MAP_HEIGHT, MAP_WIDTH};
+
+use FPS;
+
+pub const CONFIG_FILE: &str = "conway.toml";
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub width: usize,
+    pub height: usize,
+    pub fps: i32,
+    pub rule: String,
+    pub boundary: String,
+    pub palette: String,
+    pub seed: Option<u32>
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            width: MAP_WIDTH,
+            height: MAP_HEIGHT,
+            fps: FPS,
+            rule: DEFAULT_RULE.to_string(),
+            boundary: "dead".to_string(),
+            palette: "fire".to_string(),
+            seed: None
+        }
+    }
+}
+
+impl Config {
+    // A missing file just means the defaults
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(format!("couldn't read {}: {}", path.display(), e))
+        };
+        toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_settings_keep_their_defaults() {
+        let config: Config = toml::from_str("width = 400\nrule = \"B36/S23\"\n").unwrap();
+        assert_eq!(config.width, 400);
+        assert_eq!(config.rule, "B36/S23");
+        assert_eq!(config.height, MAP_HEIGHT);
+        assert_eq!(config.seed, None);
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        assert!(toml::from_str::<Config>("width = \"wide\"").is_err());
+        assert!(toml::from_str::<Config>("colour = \"red\"").is_err());
+        assert!(toml::from_str::<Config>("width = ").is_err());
+    }
+}
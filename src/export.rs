@@ -0,0 +1,99 @@
+// Image export for the tcod frontend.  This works from the map itself rather
+// than the console, so it captures the whole board, not just the viewport.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use gif::{Encoder, Frame, Repeat};
+use image::{Rgb, RgbImage};
+use tcod::Color;
+
+use conway_rs::{Cell, Map};
+
+// Each cell becomes a block this many pixels wide, and `aspect` times as
+// tall, so exports can match the shape of the font's characters
+const CELL_PIXELS: u32 = 4;
+const GIF_CELL_PIXELS: u32 = 2;
+
+// Width and height in pixels of a cell `width` pixels wide
+fn cell_size(width: u32, aspect: f32) -> (u32, u32) {
+    (width, ((width as f32 * aspect).round() as u32).max(1))
+}
+
+pub fn save_png<F>(map: &Map, path: &Path, aspect: f32, color: F) -> Result<(), String>
+    where F: Fn(&Cell) -> Color
+{
+    let (cell_w, cell_h) = cell_size(CELL_PIXELS, aspect);
+    let mut img = RgbImage::new(map.width as u32 * cell_w, map.height as u32 * cell_h);
+    for (px, py, pixel) in img.enumerate_pixels_mut() {
+        let c = color(map.cell((px / cell_w) as usize, (py / cell_h) as usize));
+        *pixel = Rgb([c.r, c.g, c.b]);
+    }
+    img.save(path).map_err(|e| format!("couldn't save {}: {}", path.display(), e))
+}
+
+// Records one GIF frame per generation.  Frames are drawn with indices into
+// the palette the recorder was created with.
+pub struct Recorder {
+    encoder: Encoder<BufWriter<File>>,
+    width: u16,
+    height: u16,
+    cell_w: u32,
+    cell_h: u32,
+    frames: u32,
+    max_frames: u32
+}
+
+impl Recorder {
+    pub fn create(path: &Path, map: &Map, palette: &[Color], max_frames: u32, aspect: f32)
+                  -> Result<Recorder, String> {
+        let err = |e: &dyn fmt::Display| format!("couldn't record to {}: {}", path.display(), e);
+        let (cell_w, cell_h) = cell_size(GIF_CELL_PIXELS, aspect);
+        let width = map.width as u32 * cell_w;
+        let height = map.height as u32 * cell_h;
+        if width > u16::MAX as u32 || height > u16::MAX as u32 {
+            return Err(err(&"map is too large for a GIF"));
+        }
+        let rgb: Vec<u8> = palette.iter().flat_map(|c| vec![c.r, c.g, c.b]).collect();
+        let file = File::create(path).map_err(|e| err(&e))?;
+        let mut encoder = Encoder::new(BufWriter::new(file), width as u16, height as u16, &rgb)
+            .map_err(|e| err(&e))?;
+        encoder.set_repeat(Repeat::Infinite).map_err(|e| err(&e))?;
+        Ok(Recorder { encoder, width: width as u16, height: height as u16, cell_w, cell_h, frames: 0, max_frames })
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.frames >= self.max_frames
+    }
+
+    // Append the current board, shown for `delay` hundredths of a second
+    pub fn add_frame<F>(&mut self, map: &Map, delay: u16, shade: F) -> Result<(), String>
+        where F: Fn(&Cell) -> usize
+    {
+        if self.is_full() { return Ok(()) };
+        if map.width as u32 * self.cell_w != self.width as u32
+            || map.height as u32 * self.cell_h != self.height as u32 {
+            return Err("can't record: the board changed size".to_string());
+        }
+        let mut pixels = Vec::with_capacity(self.width as usize * self.height as usize);
+        for py in 0..self.height as u32 {
+            for px in 0..self.width as u32 {
+                let cell = map.cell((px / self.cell_w) as usize, (py / self.cell_h) as usize);
+                pixels.push(shade(cell) as u8);
+            }
+        }
+        let mut frame = Frame::from_indexed_pixels(self.width, self.height, pixels, None);
+        frame.delay = delay;
+        self.encoder.write_frame(&frame).map_err(|e| format!("couldn't write GIF frame: {}", e))?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    // Write the GIF trailer and flush everything to disk
+    pub fn finish(self) -> Result<(), String> {
+        let err = |e: io::Error| format!("couldn't finish recording: {}", e);
+        self.encoder.into_inner().map_err(err)?.flush().map_err(err)
+    }
+}
@@ -0,0 +1,113 @@
+use map::Map;
+
+// Glyphs are 5 cells wide and 7 tall, with a blank column between letters
+pub const GLYPH_WIDTH: usize = 5;
+pub const GLYPH_HEIGHT: usize = 7;
+
+// A tiny bitmap font covering letters, digits and a little punctuation.  Each
+// row is five bits, the highest bit being the leftmost cell.
+const FONT: [(char, [u8; GLYPH_HEIGHT]); 42] = [
+    ('A', [0x0e, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11]),
+    ('B', [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e]),
+    ('C', [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e]),
+    ('D', [0x1e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x1e]),
+    ('E', [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f]),
+    ('F', [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10]),
+    ('G', [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f]),
+    ('H', [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11]),
+    ('I', [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e]),
+    ('J', [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c]),
+    ('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
+    ('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f]),
+    ('M', [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11]),
+    ('N', [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+    ('O', [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e]),
+    ('P', [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10]),
+    ('Q', [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d]),
+    ('R', [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11]),
+    ('S', [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e]),
+    ('T', [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e]),
+    ('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04]),
+    ('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a]),
+    ('X', [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11]),
+    ('Y', [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04]),
+    ('Z', [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f]),
+    ('0', [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e]),
+    ('1', [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e]),
+    ('2', [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f]),
+    ('3', [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e]),
+    ('4', [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02]),
+    ('5', [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e]),
+    ('6', [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e]),
+    ('7', [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e]),
+    ('9', [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c]),
+    ('.', [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c]),
+    (',', [0x00, 0x00, 0x00, 0x00, 0x0c, 0x04, 0x08]),
+    ('!', [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04]),
+    ('?', [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04]),
+    ('-', [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00]),
+    (' ', [0x00; GLYPH_HEIGHT])
+];
+
+impl Map {
+    // Write `text` in block letters with its top-left corner at (x, y).
+    // Lower case is drawn as upper case, characters the font lacks as '?',
+    // and anything running off the map is dropped.  Only the letters' own
+    // cells are brought to life; the gaps around them are left alone.
+    pub fn stamp_text(&mut self, text: &str, x: usize, y: usize) {
+        for (n, c) in text.chars().enumerate() {
+            let rows = glyph(c.to_ascii_uppercase());
+            let left = x + n * (GLYPH_WIDTH + 1);
+            for (j, row) in rows.iter().enumerate() {
+                for i in 0..GLYPH_WIDTH {
+                    if row & (0x10 >> i) != 0 {
+                        self.paint((left + i) as i32, (y + j) as i32, true);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Width of `text` in cells when stamped
+pub fn text_width(text: &str) -> usize {
+    (text.chars().count() * (GLYPH_WIDTH + 1)).saturating_sub(1)
+}
+
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    let find = |c| FONT.iter().find(|g| g.0 == c).map(|g| g.1);
+    find(c).or_else(|| find('?')).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rules::{Ruleset, DEFAULT_RULE};
+
+    #[test]
+    fn stamps_block_letters() {
+        let mut map = Map::new(40, 10, Ruleset::parse(DEFAULT_RULE).unwrap());
+        map.stamp_text("Hi", 1, 1);
+        // H's two uprights and crossbar, and I's serifs and stem
+        assert_eq!(map.live_cells(), 7 * 2 + 3 + 3 * 2 + 5);
+        // I's stem is in the middle, so it stops short of the full width
+        assert_eq!(map.bounding_box(), Some((1, 1, 10, 7)));
+        assert_eq!(text_width("Hi"), 11);
+        // Letters the font lacks come out as question marks
+        let mut unknown = Map::new(40, 10, Ruleset::parse(DEFAULT_RULE).unwrap());
+        let mut question = Map::new(40, 10, Ruleset::parse(DEFAULT_RULE).unwrap());
+        unknown.stamp_text("~", 0, 0);
+        question.stamp_text("?", 0, 0);
+        assert_eq!(unknown.to_ascii(), question.to_ascii());
+    }
+
+    #[test]
+    fn drops_text_off_the_map() {
+        let mut map = Map::new(8, 8, Ruleset::parse(DEFAULT_RULE).unwrap());
+        // Only the top of the L's upright fits
+        map.stamp_text("LIFE", 4, 4);
+        assert_eq!(map.bounding_box(), Some((4, 4, 4, 7)));
+    }
+}
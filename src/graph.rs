@@ -0,0 +1,58 @@
+// A small bar graph of recent population counts, drawn in a corner of the
+// screen so you can see at a glance whether a board is growing, dying out or
+// settling down.
+
+use std::collections::VecDeque;
+
+use tcod::colors as color;
+use tcod::{BackgroundFlag, Console, RootConsole, TextAlignment};
+
+// Size of the graph in console cells.  Each column is the average of
+// SAMPLES_PER_COLUMN generations, so it covers the last few hundred.
+pub const GRAPH_WIDTH: i32 = 32;
+pub const GRAPH_HEIGHT: i32 = 5;
+const SAMPLES_PER_COLUMN: usize = 10;
+
+pub struct PopulationGraph {
+    // Oldest first
+    samples: VecDeque<i32>
+}
+
+impl PopulationGraph {
+    pub fn new() -> PopulationGraph {
+        PopulationGraph { samples: VecDeque::new() }
+    }
+
+    // Record the population after a tick, forgetting the oldest sample once
+    // the graph is full
+    pub fn push(&mut self, population: i32) {
+        if self.samples.len() == GRAPH_WIDTH as usize * SAMPLES_PER_COLUMN {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(population);
+    }
+
+    // Draw the graph in the bottom right corner, scaled so the largest
+    // population seen fills its height
+    pub fn draw(&self, root: &mut RootConsole) {
+        let left = root.width() - GRAPH_WIDTH;
+        let top = root.height() - GRAPH_HEIGHT;
+        let samples: Vec<i32> = self.samples.iter().cloned().collect();
+        let columns: Vec<i32> = samples.chunks(SAMPLES_PER_COLUMN)
+            .map(|c| (c.iter().map(|&p| p as i64).sum::<i64>() / c.len() as i64) as i32)
+            .collect();
+        let max = columns.iter().cloned().max().unwrap_or(0).max(1);
+        for x in 0..GRAPH_WIDTH {
+            // Round up so any live cells at all show as a bar
+            let bar = columns.get(x as usize)
+                .map_or(0, |&p| ((p as i64 * GRAPH_HEIGHT as i64 + max as i64 - 1) / max as i64) as i32);
+            for y in 0..GRAPH_HEIGHT {
+                let bg = if GRAPH_HEIGHT - y <= bar { color::LIGHT_GREEN } else { color::BLACK };
+                root.put_char_ex(left + x, top + y, ' ', color::WHITE, bg);
+            }
+        }
+        root.set_default_foreground(color::WHITE);
+        root.set_default_background(color::BLACK);
+        root.print_ex(left, top, BackgroundFlag::None, TextAlignment::Left, format!("{}", max));
+    }
+}
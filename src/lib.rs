@@ -0,0 +1,45 @@
+// The Game of Life simulation, independent of any frontend.  Nothing in here
+// depends on tcod so it can be built and tested without graphics libraries.
+// Events are logged through the `log` crate, and go nowhere unless the
+// frontend installs a logger.
+
+#[macro_use]
+extern crate log;
+#[cfg(feature = "image")]
+extern crate image;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "session")]
+extern crate serde;
+#[cfg(feature = "session")]
+extern crate serde_json;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+
+mod analysis;
+mod cells;
+mod font;
+mod life106;
+mod map;
+mod noise;
+mod pattern;
+pub mod patterns;
+#[cfg(feature = "image")]
+mod picture;
+mod rle;
+mod rng;
+mod rules;
+#[cfg(feature = "session")]
+mod session;
+mod sparse;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use analysis::GLIDER_PERIOD;
+pub use font::{text_width, GLYPH_HEIGHT};
+pub use map::{BoundaryMode, Cell, Map, Neighbourhood, SimKind, SymmetryKind, MAP_HEIGHT, MAP_WIDTH, NOISE_HORI,
+              NOISE_VERT, MAX_RADIUS, REWIND_DEPTH, TRAIL_LENGTH};
+pub use pattern::{Edge, Orientation, Pattern};
+pub use rng::time_seed;
+pub use rules::{Ruleset, DEFAULT_RULE};
+pub use sparse::SparseMap;
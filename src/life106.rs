@@ -0,0 +1,92 @@
+use map::Map;
+use pattern::Pattern;
+
+const LIFE106_HEADER: &str = "#Life 1.06";
+
+// Parse the Life 1.06 format: a `#Life 1.06` line, then whitespace separated
+// `x y` coordinates of the live cells, which may be negative
+fn parse_life106(text: &str) -> Result<Vec<(i32, i32)>, String> {
+    let mut lines = text.lines();
+    match lines.next() {
+        Some(header) if header.trim() == LIFE106_HEADER => {},
+        _ => return Err(format!("missing '{}' header", LIFE106_HEADER))
+    }
+    let numbers = lines.flat_map(|l| l.split_whitespace())
+        .map(|n| n.parse::<i32>().map_err(|_| format!("invalid coordinate '{}' in Life 1.06 pattern", n)))
+        .collect::<Result<Vec<i32>, String>>()?;
+    if numbers.len() % 2 != 0 {
+        return Err("Life 1.06 pattern ends partway through a coordinate pair".to_string());
+    }
+    Ok(numbers.chunks(2).map(|pair| (pair[0], pair[1])).collect())
+}
+
+impl Map {
+    // Load a Life 1.06 pattern with its (0, 0) at the given origin.  Like
+    // stamp(), this clears the pattern's bounding box first, and rejects
+    // patterns that don't fit on the map, leaving it untouched.
+    pub fn load_life106(&mut self, text: &str, origin_x: usize, origin_y: usize) -> Result<(), String> {
+        let cells = parse_life106(text)?;
+        let (left, top) = match (cells.iter().map(|c| c.0).min(), cells.iter().map(|c| c.1).min()) {
+            (Some(x), Some(y)) => (x, y),
+            _ => return Ok(())
+        };
+        let right = cells.iter().map(|c| c.0).max().unwrap_or(left);
+        let bottom = cells.iter().map(|c| c.1).max().unwrap_or(top);
+        // Measured in i64, since coordinates at both ends of i32 span more
+        // than it holds
+        let width = right as i64 - left as i64 + 1;
+        let height = bottom as i64 - top as i64 + 1;
+        if width > self.width as i64 || height > self.height as i64 {
+            return Err(format!("{}x{} pattern is larger than the {}x{} map", width, height, self.width, self.height));
+        }
+        let (x, y) = (origin_x as i64 + left as i64, origin_y as i64 + top as i64);
+        if x < 0 || y < 0 {
+            return Err(format!("pattern runs off the map at ({}, {})", x, y));
+        }
+        let pattern = Pattern {
+            width: width as usize,
+            height: height as usize,
+            cells: cells.iter()
+                .map(|&(i, j)| ((i as i64 - left as i64) as usize, (j as i64 - top as i64) as usize))
+                .collect()
+        };
+        self.stamp(&pattern, x as usize, y as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rules::{Ruleset, DEFAULT_RULE};
+
+    fn conway() -> Map {
+        Map::new(20, 20, Ruleset::parse(DEFAULT_RULE).unwrap())
+    }
+
+    #[test]
+    fn loads_cells_around_the_origin() {
+        let mut map = conway();
+        // A glider, centred on its middle cell
+        map.load_life106("#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n", 5, 5).unwrap();
+        let live: Vec<(usize, usize)> = map.live_iter().collect();
+        assert_eq!(live, [(5, 4), (6, 5), (4, 6), (5, 6), (6, 6)]);
+        // Nothing to place is fine
+        map.load_life106("#Life 1.06\n", 5, 5).unwrap();
+        assert_eq!(map.live_cells(), 5);
+    }
+
+    #[test]
+    fn rejects_bad_patterns() {
+        let mut map = conway();
+        assert!(map.load_life106("0 0\n", 5, 5).is_err());
+        assert!(map.load_life106("#Life 1.06\n0 0\n1\n", 5, 5).is_err());
+        assert!(map.load_life106("#Life 1.06\n0 x\n", 5, 5).is_err());
+        // Off the top left, and off the bottom right
+        assert!(map.load_life106("#Life 1.06\n-6 0\n", 5, 5).is_err());
+        assert!(map.load_life106("#Life 1.06\n0 15\n", 5, 5).is_err());
+        // Further apart than i32 can measure
+        assert!(map.load_life106("#Life 1.06\n-2000000000 0\n2000000000 0\n", 5, 5).is_err());
+        assert!(map.load_life106("#Life 1.06\n0 -2147483648\n0 2147483647\n", 5, 5).is_err());
+        assert_eq!(map.live_cells(), 0);
+    }
+}
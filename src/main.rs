@@ -1,37 +1,554 @@
 extern crate tcod;
+extern crate conway_rs;
+extern crate env_logger;
+extern crate gif;
+extern crate image;
+#[macro_use]
+extern crate log;
+extern crate serde;
+extern crate toml;
+#[cfg(feature = "rayon")]
+extern crate rayon;
 
-use tcod::{Console, FontLayout, FontType, Renderer, RootConsole};
+use tcod::{BackgroundFlag, Console, FontLayout, FontType, Renderer, RootConsole, TextAlignment};
 use tcod::system;
 use tcod::input;
 use tcod::colors as color;
-use tcod::noise;
+use tcod::Color;
 
+use conway_rs::{patterns, text_width, time_seed, BoundaryMode, Cell, Edge, Map, Neighbourhood, Orientation, Pattern,
+                Ruleset, SimKind, SparseMap, SymmetryKind, GLIDER_PERIOD, GLYPH_HEIGHT, NOISE_HORI, NOISE_VERT,
+                MAX_RADIUS, REWIND_DEPTH, TRAIL_LENGTH};
+
+use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
 use std::thread;
 use std::time::{Duration, Instant};
 
-const MAP_WIDTH: usize = 300;
-const MAP_HEIGHT: usize = 80;
+mod command;
+mod config;
+mod export;
+mod graph;
+mod palette;
+
+use command::Command;
+use config::{Config, CONFIG_FILE};
+use graph::{PopulationGraph, GRAPH_HEIGHT, GRAPH_WIDTH};
+use palette::{Palette, PALETTES};
+
 const SCREEN_WIDTH: i32 = 80;
 const SCREEN_HEIGHT: i32 = 40;
+// ▀ in the font's CP437 layout
+const UPPER_HALF_BLOCK: char = '\u{df}';
+// Live and dead characters cycled through with g; the first are the defaults
+const GLYPHS: [(char, char); 4] = [('*', ' '), ('\u{db}', ' '), ('O', '.'), ('#', ' ')];
+// Tint for the edges of a wrapping map
+const SEAM_COLOR: Color = Color { r: 0, g: 63, b: 95 };
+// Cells the last tick brought to life and killed, with d
+const BORN_COLOR: Color = Color { r: 0, g: 160, b: 0 };
+const DIED_COLOR: Color = Color { r: 128, g: 0, b: 0 };
+// Outline of the area y mode selects for saving
+const SELECTION_COLOR: Color = Color { r: 0, g: 95, b: 191 };
+// The keyboard cursor in half block mode, where it can't be inverted
+const CURSOR_COLOR: Color = Color { r: 255, g: 255, b: 0 };
 const FPS: i32 = 25;
+// Bounds for the adjustable simulation speed, in ticks per second
+const MIN_TICK_RATE: u32 = 1;
+const MAX_TICK_RATE: u32 = 120;
+// How far back the measured gen/s looks
+const SIM_RATE_WINDOW: Duration = Duration::from_secs(1);
+// How long the edge warning stays on, and then off, while it flashes
+const EDGE_FLASH: Duration = Duration::from_millis(500);
+// How much of the way to the live cells the view moves each tick in follow
+// mode
+const FOLLOW_EASE: f32 = 0.1;
+// How far the arrow keys scroll, normally and with shift held
+const PAN_STEP: i32 = 1;
+const FAST_PAN_STEP: i32 = 10;
+// Generations run by --headless unless --generations says otherwise
+const HEADLESS_GENERATIONS: u64 = 1000;
+// How far [ and ] move the noise threshold
+const NOISE_THRESHOLD_STEP: f32 = 0.05;
+// Fraction of cells alive after a random fill, unless --density is given
+const DENSITY: f32 = 0.35;
+// Size and fill of the blob brush's discs, unless --blob-radius and
+// --blob-density are given
+const BLOB_RADIUS: usize = 4;
+const BLOB_DENSITY: f32 = 0.5;
+// Pixels darker than this start alive with --init image
+const IMAGE_THRESHOLD: u8 = 128;
+// Looked for in the current directory, unless --font is given
+const FONT_FILE: &str = "BrogueFont3.png";
+// Where F5 and F9 save and load, unless --session is given
+const SESSION_FILE: &str = "conway-session.json";
+// Default cap on the length of a GIF recording
+const RECORD_FRAMES: u32 = 500;
+// Furthest :goto will tick ahead in one go, since the frame loop waits on it
+const MAX_GOTO_AHEAD: u64 = 10_000;
+// BrogueFont3's characters are 12x18, so cells on screen are half again as
+// tall as they are wide
+const EXPORT_ASPECT: f32 = 1.5;
 
-// Chosen purely because it looks good
-const NOISE_VERT: f32 = 12.0;
-const NOISE_HORI: f32  = 40.0;
+const USAGE: &str = "Usage: conway-rs [options]
 
-#[derive(Copy, Clone)]
-struct Cell {
-    alive: bool,
-    linger: u8,
-    flip: bool
+Options:
+    --width <cells>     Map width (default 300)
+    --height <cells>    Map height (default 80)
+    --halfblock         Draw two rows of cells per screen row
+    --alive-char <c>    Character live cells are drawn with (default *)
+    --dead-char <c>     Character dead cells are drawn with (default space)
+    --fit               Size the map to fill the window exactly, overriding
+                        --width and --height
+    --fps <n>           Frames per second (default 25)
+    --rule <rule>       Rule in B/S notation, with /C<states> for Generations
+                        rules, or one of life, highlife, seeds,
+                        lifewithoutdeath, daynight or antilife
+                        (default B3/S23).  Counts may be split into
+                        orthogonal:diagonal neighbours, e.g. B1:2/S23:*
+    --boundary <mode>   Map edges: dead, wrap, cylinder-x or mirror
+                        (default dead)
+    --sim <kind>        Automaton to run: life, brians-brain or immigration
+                        (default life)
+    --neighbourhood <n> Cells counted as neighbours: moore, von-neumann or
+                        hex (default moore)
+    --radius <r>        Neighbourhood radius, up to 10 (default 1)
+    --birth-chance <p>  Chance a cell that should be born really is, from 0
+                        to 1; the rolls follow --seed (default 1)
+    --survival-chance <p>
+                        Chance a cell that should survive really does
+                        (default 1)
+    --palette <name>    Trail colours: fire, ice, greyscale or matrix
+                        (default fire)
+    --palette-file <file>
+                        Load trail colours from ten r,g,b lines
+    --seed <n>          Seed for the starting board (default random)
+    --init <kind>       How the board is seeded: noise, random, symmetric or
+                        image (default noise)
+    --symmetry <s>      Symmetry of --init symmetric soups: none, mirrorx,
+                        mirrorxy or rotate4 (default mirrorxy)
+    --image <file>      Picture for --init image, stretched over the map
+    --image-threshold <n>
+                        Brightness from 0 to 255 below which an --image
+                        pixel starts alive (default 128)
+    --noise-scale-x <s> Noise cycles across the map with --init noise
+                        (default 40)
+    --noise-scale-y <s> Noise cycles down the map (default 12)
+    --noise-threshold <t>
+                        Noise value a cell needs to start alive, from -1 to
+                        1; higher is sparser (default 0)
+    --octaves <n>       Layers of finer noise to add for a more detailed
+                        board (default 1)
+    --persistence <p>   Weight of each layer relative to the one before
+                        (default 0.5)
+    --density <d>       Fraction of cells alive in random fills and soups
+                        (default 0.35)
+    --blob-radius <r>   Radius of the blob brush's discs (default 4)
+    --blob-density <d>  Fraction of cells alive in them (default 0.5)
+    --text <text>       Start from the text in block letters instead
+    --font <file>       Bitmap font to draw with (default BrogueFont3.png)
+    --font-layout <l>   How the font's glyphs are laid out: ascii-in-row,
+                        ascii-in-col or tcod (default ascii-in-row)
+    --renderer <r>      Renderer to draw with: sdl, opengl or glsl
+                        (default sdl)
+    --screenshot-dir <dir>
+                        Where screenshots and F2 selections are saved
+                        (default .)
+    --session <file>    Where F5 saves and F9 loads the session
+                        (default conway-session.json)
+    --record <file>     GIF file that recordings are written to
+    --record-frames <n> Most frames a recording can hold (default 500)
+    --export-aspect <r> Height of each cell in screenshots and recordings as
+                        a multiple of its width (default 1.5, the shape of
+                        the default font)
+    --jobs <n>          Threads used to compute each generation (needs the
+                        rayon feature, default 1)
+    --max-frame-ms <n>  Skip drawing a frame after one that spent longer than
+                        n milliseconds drawing and ticking, to keep large
+                        boards responsive
+    --stop-at <n>       Pause when the simulation reaches generation n; Enter
+                        carries on past it
+    --rewind-depth <n>  Past generations kept for stepping back (default 100)
+    --trail-length <n>  Generations a dead cell's trail takes to fade, up to
+                        255; 1 turns trails off (default 9)
+    --margin <n>        Keep a band n cells wide around the edge dead, as if
+                        the map ended there (default 0)
+    --verbose           Log what happens to stderr; RUST_LOG can pick the
+                        level (default info)
+    --headless          Run without a window and print statistics; exits
+                        with status 2 if every cell dies
+    --generations <n>   Generations to run headless (default 1000)
+    --backend <b>       How a headless run stores the board: dense, or sparse
+                        for a big map with few live cells (default dense)
+    --print-every <n>   Print the board as text every n generations when
+                        running headless
+    --dump-rle <file>   Save the final board as RLE when running headless
+    -h, --help          Show this message
+
+Defaults for --width, --height, --fps, --rule, --boundary, --palette and
+--seed can also be set in conway.toml in the current directory.";
+
+// How a fresh board is filled: Perlin noise, uniformly at random, at random
+// with a symmetry, or from the --image file
+#[derive(Copy, Clone, PartialEq)]
+enum InitKind {
+    Noise,
+    Random,
+    Symmetric,
+    Image
 }
 
-struct Map {
-    map: [[Cell; MAP_HEIGHT]; MAP_WIDTH],
-    height: usize,
+// What a headless run ticks: the Map itself, or a SparseMap copied from it
+#[derive(Copy, Clone, PartialEq)]
+enum Backend {
+    Dense,
+    Sparse
+}
+
+// What the left mouse button does, cycled with q: draw live cells while
+// held, flip the cell clicked, or drop a blob of random cells around it
+#[derive(Copy, Clone, PartialEq)]
+enum Brush {
+    Draw,
+    Toggle,
+    Blob
+}
+
+impl Brush {
+    fn next(self) -> Brush {
+        match self {
+            Brush::Draw => Brush::Toggle,
+            Brush::Toggle => Brush::Blob,
+            Brush::Blob => Brush::Draw
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Brush::Draw => "draw brush",
+            Brush::Toggle => "toggle brush",
+            Brush::Blob => "blob brush"
+        }
+    }
+}
+
+// Settings chosen on the command line
+struct Options {
     width: usize,
-    o_x: i32,
-    o_y: i32
+    height: usize,
+    fps: i32,
+    halfblock: bool,
+    // Characters for live and dead cells
+    glyphs: (char, char),
+    // Make the map exactly the size of the view, so nothing is off screen
+    fit: bool,
+    rule: Ruleset,
+    boundary: BoundaryMode,
+    kind: SimKind,
+    neighbourhood: Neighbourhood,
+    radius: usize,
+    // Folded into the rule once it's known
+    birth_chance: f32,
+    survival_chance: f32,
+    // Index into PALETTES
+    palette: usize,
+    custom_palette: Option<Palette>,
+    seed: Option<u32>,
+    init: InitKind,
+    symmetry: SymmetryKind,
+    image: Option<PathBuf>,
+    // Brightness below which an --image pixel starts alive
+    image_threshold: u8,
+    noise_scale_x: f32,
+    noise_scale_y: f32,
+    noise_threshold: f32,
+    noise_octaves: u32,
+    noise_persistence: f32,
+    density: f32,
+    // The discs the blob brush drops
+    blob_radius: usize,
+    blob_density: f32,
+    text: Option<String>,
+    font: String,
+    font_layout: FontLayout,
+    renderer: Renderer,
+    screenshot_dir: PathBuf,
+    session: PathBuf,
+    jobs: usize,
+    // Time a frame may spend drawing and ticking before the next frame
+    // goes undrawn
+    max_frame: Option<Duration>,
+    record: Option<PathBuf>,
+    record_frames: u32,
+    export_aspect: f32,
+    rewind_depth: usize,
+    trail_length: u8,
+    margin: usize,
+    // Generation to pause at
+    stop_at: Option<u64>,
+    verbose: bool,
+    headless: bool,
+    generations: u64,
+    backend: Backend,
+    print_every: Option<u64>,
+    dump_rle: Option<PathBuf>
+}
+
+impl Options {
+    // Start from the config file's settings and apply the flags on top
+    fn parse<I: Iterator<Item = String>>(mut args: I, config: &Config) -> Result<Options, String> {
+        let in_config = |e: String| format!("{}: {}", CONFIG_FILE, e);
+        let mut opts = Options {
+            width: config.width,
+            height: config.height,
+            fps: config.fps,
+            halfblock: false,
+            glyphs: GLYPHS[0],
+            fit: false,
+            rule: Ruleset::parse(&config.rule).map_err(in_config)?,
+            boundary: parse_boundary(&config.boundary).map_err(in_config)?,
+            kind: SimKind::Life,
+            neighbourhood: Neighbourhood::Moore,
+            radius: 1,
+            birth_chance: 1.0,
+            survival_chance: 1.0,
+            palette: Palette::find(&config.palette).map_err(in_config)?,
+            custom_palette: None,
+            seed: config.seed,
+            init: InitKind::Noise,
+            symmetry: SymmetryKind::MirrorXY,
+            image: None,
+            image_threshold: IMAGE_THRESHOLD,
+            noise_scale_x: NOISE_HORI,
+            noise_scale_y: NOISE_VERT,
+            noise_threshold: 0.0,
+            noise_octaves: 1,
+            noise_persistence: 0.5,
+            density: DENSITY,
+            blob_radius: BLOB_RADIUS,
+            blob_density: BLOB_DENSITY,
+            text: None,
+            font: FONT_FILE.to_string(),
+            font_layout: FontLayout::AsciiInRow,
+            renderer: Renderer::SDL,
+            screenshot_dir: PathBuf::from("."),
+            session: PathBuf::from(SESSION_FILE),
+            jobs: 1,
+            max_frame: None,
+            record: None,
+            record_frames: RECORD_FRAMES,
+            export_aspect: EXPORT_ASPECT,
+            rewind_depth: REWIND_DEPTH,
+            trail_length: TRAIL_LENGTH,
+            margin: 0,
+            stop_at: None,
+            verbose: false,
+            headless: false,
+            generations: HEADLESS_GENERATIONS,
+            backend: Backend::Dense,
+            print_every: None,
+            dump_rle: None
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--width" => opts.width = parse_value(&arg, args.next())?,
+                "--height" => opts.height = parse_value(&arg, args.next())?,
+                "--fps" => opts.fps = parse_value(&arg, args.next())?,
+                "--halfblock" => opts.halfblock = true,
+                "--fit" => opts.fit = true,
+                "--alive-char" => opts.glyphs.0 = parse_glyph(&arg, args.next())?,
+                "--dead-char" => opts.glyphs.1 = parse_glyph(&arg, args.next())?,
+                "--rule" => opts.rule = Ruleset::parse(&require_value(&arg, args.next())?)?,
+                "--boundary" => opts.boundary = parse_boundary(&require_value(&arg, args.next())?)?,
+                "--sim" => opts.kind = match require_value(&arg, args.next())?.as_str() {
+                    "life" => SimKind::Life,
+                    "brians-brain" => SimKind::BriansBrain,
+                    "immigration" => SimKind::Immigration,
+                    other => return Err(format!("unknown automaton '{}'", other))
+                },
+                "--neighbourhood" => opts.neighbourhood = match require_value(&arg, args.next())?.as_str() {
+                    "moore" => Neighbourhood::Moore,
+                    "von-neumann" => Neighbourhood::VonNeumann,
+                    "hex" => Neighbourhood::Hex,
+                    other => return Err(format!("unknown neighbourhood '{}'", other))
+                },
+                "--radius" => opts.radius = parse_value(&arg, args.next())?,
+                "--birth-chance" => opts.birth_chance = parse_value(&arg, args.next())?,
+                "--survival-chance" => opts.survival_chance = parse_value(&arg, args.next())?,
+                "--palette" => opts.palette = Palette::find(&require_value(&arg, args.next())?)?,
+                "--palette-file" => opts.custom_palette =
+                    Some(Palette::from_file(Path::new(&require_value(&arg, args.next())?))?),
+                "--seed" => opts.seed = Some(parse_value(&arg, args.next())?),
+                "--init" => opts.init = match require_value(&arg, args.next())?.as_str() {
+                    "noise" => InitKind::Noise,
+                    "random" => InitKind::Random,
+                    "symmetric" => InitKind::Symmetric,
+                    "image" => InitKind::Image,
+                    other => return Err(format!("unknown seeding '{}'", other))
+                },
+                "--symmetry" => opts.symmetry = match require_value(&arg, args.next())?.as_str() {
+                    "none" => SymmetryKind::None,
+                    "mirrorx" => SymmetryKind::MirrorX,
+                    "mirrorxy" => SymmetryKind::MirrorXY,
+                    "rotate4" => SymmetryKind::Rotate4,
+                    other => return Err(format!("unknown symmetry '{}' (expected none, mirrorx, mirrorxy or \
+                                                 rotate4)", other))
+                },
+                "--image" => opts.image = Some(PathBuf::from(require_value(&arg, args.next())?)),
+                "--image-threshold" => opts.image_threshold = parse_value(&arg, args.next())?,
+                "--noise-scale-x" => opts.noise_scale_x = parse_value(&arg, args.next())?,
+                "--noise-scale-y" => opts.noise_scale_y = parse_value(&arg, args.next())?,
+                "--noise-threshold" => opts.noise_threshold = parse_value(&arg, args.next())?,
+                "--octaves" => opts.noise_octaves = parse_value(&arg, args.next())?,
+                "--persistence" => opts.noise_persistence = parse_value(&arg, args.next())?,
+                "--density" => opts.density = parse_value(&arg, args.next())?,
+                "--blob-radius" => opts.blob_radius = parse_value(&arg, args.next())?,
+                "--blob-density" => opts.blob_density = parse_value(&arg, args.next())?,
+                "--text" => opts.text = Some(require_value(&arg, args.next())?),
+                "--font" => opts.font = require_value(&arg, args.next())?,
+                "--font-layout" => opts.font_layout = match require_value(&arg, args.next())?.as_str() {
+                    "ascii-in-row" => FontLayout::AsciiInRow,
+                    "ascii-in-col" => FontLayout::AsciiInCol,
+                    "tcod" => FontLayout::Tcod,
+                    other => return Err(format!("unknown font layout '{}' (expected ascii-in-row, \
+                                                 ascii-in-col or tcod)", other))
+                },
+                "--renderer" => opts.renderer = match require_value(&arg, args.next())?.as_str() {
+                    "sdl" => Renderer::SDL,
+                    "opengl" => Renderer::OpenGL,
+                    "glsl" => Renderer::GLSL,
+                    other => return Err(format!("unknown renderer '{}' (expected sdl, opengl or glsl)", other))
+                },
+                "--screenshot-dir" => opts.screenshot_dir = PathBuf::from(require_value(&arg, args.next())?),
+                "--jobs" => opts.jobs = parse_value(&arg, args.next())?,
+                "--max-frame-ms" => opts.max_frame = Some(Duration::from_millis(parse_value(&arg, args.next())?)),
+                "--session" => opts.session = PathBuf::from(require_value(&arg, args.next())?),
+                "--record" => opts.record = Some(PathBuf::from(require_value(&arg, args.next())?)),
+                "--record-frames" => opts.record_frames = parse_value(&arg, args.next())?,
+                "--export-aspect" => opts.export_aspect = parse_value(&arg, args.next())?,
+                "--rewind-depth" => opts.rewind_depth = parse_value(&arg, args.next())?,
+                "--trail-length" => opts.trail_length = parse_value(&arg, args.next())?,
+                "--margin" => opts.margin = parse_value(&arg, args.next())?,
+                "--stop-at" => opts.stop_at = Some(parse_value(&arg, args.next())?),
+                "--verbose" => opts.verbose = true,
+                "--headless" => opts.headless = true,
+                "--generations" => opts.generations = parse_value(&arg, args.next())?,
+                "--backend" => opts.backend = match require_value(&arg, args.next())?.as_str() {
+                    "dense" => Backend::Dense,
+                    "sparse" => Backend::Sparse,
+                    other => return Err(format!("unknown backend '{}' (expected dense or sparse)", other))
+                },
+                "--print-every" => opts.print_every = Some(parse_value(&arg, args.next())?),
+                "--dump-rle" => opts.dump_rle = Some(PathBuf::from(require_value(&arg, args.next())?)),
+                _ => return Err(format!("unknown option '{}'", arg))
+            }
+        }
+        if opts.fit {
+            opts.width = opts.view_width() as usize;
+            opts.height = opts.view_height() as usize;
+        }
+        if opts.width < opts.view_width() as usize || opts.height < opts.view_height() as usize {
+            return Err(format!("map must be at least as large as the {}x{} view",
+                               opts.view_width(), opts.view_height()));
+        }
+        if opts.fps <= 0 {
+            return Err("--fps must be positive".to_string());
+        }
+        if opts.jobs == 0 {
+            return Err("--jobs must be positive".to_string());
+        }
+        if opts.noise_octaves == 0 {
+            return Err("--octaves must be positive".to_string());
+        }
+        if opts.radius == 0 || opts.radius > MAX_RADIUS {
+            return Err(format!("--radius must be from 1 to {}", MAX_RADIUS));
+        }
+        if opts.trail_length == 0 {
+            return Err("--trail-length must be positive".to_string());
+        }
+        for &(flag, chance) in &[("--birth-chance", opts.birth_chance), ("--survival-chance", opts.survival_chance)] {
+            if !(0.0..=1.0).contains(&chance) {
+                return Err(format!("{} must be between 0 and 1", flag));
+            }
+        }
+        opts.rule.birth_prob = opts.birth_chance;
+        opts.rule.survival_prob = opts.survival_chance;
+        if opts.init == InitKind::Image && opts.image.is_none() {
+            return Err("--init image needs an --image file".to_string());
+        }
+        if opts.print_every == Some(0) {
+            return Err("--print-every must be positive".to_string());
+        }
+        if !opts.export_aspect.is_finite() || opts.export_aspect <= 0.0 {
+            return Err("--export-aspect must be positive".to_string());
+        }
+        if opts.record_frames == 0 {
+            return Err("--record-frames must be positive".to_string());
+        }
+        if opts.backend == Backend::Sparse && !opts.headless {
+            return Err("--backend sparse only works with --headless".to_string());
+        }
+        if opts.jobs > 1 && !cfg!(feature = "rayon") {
+            return Err("--jobs needs conway-rs to be built with the rayon feature".to_string());
+        }
+        Ok(opts)
+    }
+
+    // Columns and rows of cells on screen at once, as in view_size
+    fn view_width(&self) -> i32 {
+        if self.neighbourhood == Neighbourhood::Hex { SCREEN_WIDTH / 2 } else { SCREEN_WIDTH }
+    }
+
+    fn view_height(&self) -> i32 {
+        if self.halfblock && self.neighbourhood != Neighbourhood::Hex { SCREEN_HEIGHT * 2 } else { SCREEN_HEIGHT }
+    }
+}
+
+// A single character the font can draw: printable ASCII, which code page 437
+// shares, or the full block, given as itself rather than as \u{db}.  Other
+// characters would come out as whatever CP437 has at their code.
+fn parse_glyph(arg: &str, value: Option<String>) -> Result<char, String> {
+    let value = require_value(arg, value)?;
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some('\u{2588}'), None) => Ok('\u{db}'),
+        (Some(c), None) if c == ' ' || c.is_ascii_graphic() => Ok(c),
+        (Some(_), None) => Err(format!("the font can't draw '{}' for {}, expected printable ASCII or \u{2588}",
+                                       value, arg)),
+        _ => Err(format!("{} takes a single character, not '{}'", arg, value))
+    }
+}
+
+// The name --boundary knows a mode by
+fn boundary_name(mode: BoundaryMode) -> &'static str {
+    match mode {
+        BoundaryMode::Dead => "dead",
+        BoundaryMode::Wrap => "wrap",
+        BoundaryMode::CylinderX => "cylinder-x",
+        BoundaryMode::Mirror => "mirror"
+    }
+}
+
+fn parse_boundary(mode: &str) -> Result<BoundaryMode, String> {
+    match mode {
+        "dead" => Ok(BoundaryMode::Dead),
+        "wrap" => Ok(BoundaryMode::Wrap),
+        "cylinder-x" => Ok(BoundaryMode::CylinderX),
+        "mirror" => Ok(BoundaryMode::Mirror),
+        other => Err(format!("unknown boundary mode '{}'", other))
+    }
+}
+
+fn require_value(arg: &str, value: Option<String>) -> Result<String, String> {
+    value.ok_or_else(|| format!("{} needs a value", arg))
+}
+
+fn parse_value<T: FromStr>(arg: &str, value: Option<String>) -> Result<T, String> {
+    let value = require_value(arg, value)?;
+    value.parse().map_err(|_| format!("invalid value '{}' for {}", value, arg))
 }
 
 #[derive(PartialEq)]
@@ -41,210 +558,1184 @@ enum GameState {
     Ending
 }
 
-impl Map {
-    fn new() -> Map {
-        Map {
-            map: [[ Cell { alive: false, linger: 0, flip: false}; MAP_HEIGHT]
-                  ; MAP_WIDTH],
-            height: MAP_HEIGHT,
-            width: MAP_WIDTH,
-            o_x: (MAP_WIDTH as i32 - SCREEN_WIDTH) / 2,
-            o_y: (MAP_HEIGHT as i32 - SCREEN_HEIGHT) / 2
+// The character a cell is drawn with, and its background as one of the
+// palette's shades
+fn cell_glyph(map: &Map, cell: &Cell) -> (char, usize) {
+    match map.kind {
+        // Dying cells under a Generations rule darken as they age
+        SimKind::Life | SimKind::Immigration if cell.dying => {
+            let states = map.rule.states as usize;
+            // Never further gone than the rule has shades for
+            let age = (cell.age as usize).min(states - 2);
+            ('+', 9 * (states - 1 - age) / (states - 1))
+        },
+        SimKind::Life => {
+            let c = if cell.alive { '*' } else { ' ' };
+            (c, trail_shade(map, cell.linger))
+        },
+        // The second side's cells, and their trails, use the tinted shades
+        SimKind::Immigration => {
+            let c = if cell.alive { '*' } else { ' ' };
+            (c, trail_shade(map, cell.linger) + 10 * cell.color as usize)
+        },
+        // On, dying and off cells each get their own shade
+        SimKind::BriansBrain => {
+            if cell.alive { ('*', 9) }
+            else if cell.dying { ('+', 4) }
+            else { (' ', 0) }
         }
     }
+}
+
+// The palette shade for a linger level: the map's trail length is spread
+// over shades 1 to 9, rounding up so the last of a trail still shows
+fn trail_shade(map: &Map, linger: u8) -> usize {
+    let length = map.trail_length.max(1) as usize;
+    ((linger as usize * 9).div_ceil(length)).min(9)
+}
+
+// Width and height of the part of the map on screen.  Hex grids draw each
+// cell two characters wide so that odd rows can be shifted by half a cell;
+// they ignore `halfblock` and zooming.
+fn view_size(map: &Map, style: &Style) -> (i32, i32) {
+    if map.neighbourhood == Neighbourhood::Hex {
+        (SCREEN_WIDTH / 2, SCREEN_HEIGHT)
+    } else if style.halfblock {
+        (SCREEN_WIDTH * style.zoom, SCREEN_HEIGHT * 2 * style.zoom)
+    } else {
+        (SCREEN_WIDTH * style.zoom, SCREEN_HEIGHT * style.zoom)
+    }
+}
+
+// The furthest out the view can zoom: just far enough to fit the whole map
+fn max_zoom(map: &Map, style: &Style) -> i32 {
+    let rows = if style.halfblock { SCREEN_HEIGHT * 2 } else { SCREEN_HEIGHT };
+    let fit = |cells: usize, screen: i32| (cells as i32 + screen - 1) / screen;
+    fit(map.width, SCREEN_WIDTH).max(fit(map.height, rows)).max(1)
+}
 
+// Zoom in or out by `step`, keeping the middle of the view where it is
+fn zoom_by(map: &mut Map, style: &mut Style, step: i32) {
+    if map.neighbourhood == Neighbourhood::Hex { return };
+    let (view_w, view_h) = view_size(map, style);
+    let (mid_x, mid_y) = (map.o_x + view_w / 2, map.o_y + view_h / 2);
+    style.zoom = (style.zoom + step).clamp(1, max_zoom(map, style));
+    let (view_w, view_h) = view_size(map, style);
+    map.center_view_on(mid_x, mid_y, view_w, view_h);
+}
+
+// The map cell under screen position (x, y) on a hex grid, if any
+fn hex_cell(map: &Map, x: i32, y: i32) -> (i32, i32) {
+    let row = y + map.o_y;
+    ((x - (row & 1)).div_euclid(2) + map.o_x, row)
+}
 
-    fn inc_linger(&mut self, x: usize, y: usize) {
-        if self.map[x][y].linger < 9 { self.map[x][y].linger += 1; }
+// How the board is drawn
+#[derive(Copy, Clone)]
+struct Style {
+    // Leave out trails and dying cells and draw live cells plainly over black
+    crisp: bool,
+    // Show two rows of cells per screen row, the top one as the foreground of
+    // a half block and the bottom one as its background
+    halfblock: bool,
+    // Highlight the edges of the map that wrap around
+    seams: bool,
+    // Each character shows a zoom x zoom block of cells
+    zoom: i32,
+    // Characters live and dead cells are drawn with
+    glyphs: (char, char),
+    // Shade live cells by how long they've lived instead of by their trails
+    by_age: bool,
+    // Pick out the cells born and killed by the last tick
+    changes: bool,
+    // Show a wrapping map shrunk and tiled 3 x 3, see display_tiled
+    tiled: bool
+}
+
+// Whether the map is being shown tiled rather than as usual, which only
+// makes sense when it wraps both ways
+fn tiling(map: &Map, style: &Style) -> bool {
+    style.tiled && map.boundary == BoundaryMode::Wrap
+}
+
+// Whether any cell in the n x n block at (i, j) was born or died in the last
+// tick, as in block_cell
+fn block_changed(map: &Map, i: usize, j: usize, n: usize) -> bool {
+    (j..(j + n).min(map.height)).any(|y| (i..(i + n).min(map.width)).any(|x| map.changed(x, y)))
+}
+
+// The palette shade for a cell that has lived `alive_for` generations: the
+// brightest for newborns, dimming with each doubling of its age so still
+// lifes stand out from the churn around them
+fn age_shade(alive_for: u16) -> usize {
+    9 - ((alive_for as u32 + 1).ilog2() as usize).min(6)
+}
+
+// One cell standing in for the n x n block at (i, j): alive if any of them
+// are, with the brightest trail among them
+fn block_cell(map: &Map, i: usize, j: usize, n: usize) -> Cell {
+    let mut block = *map.cell(i, j);
+    for y in j..(j + n).min(map.height) {
+        for x in i..(i + n).min(map.width) {
+            let cell = map.cell(x, y);
+            if cell.alive && !block.alive {
+                block = *cell;
+            }
+            block.dying |= cell.dying && !block.alive;
+            block.linger = block.linger.max(cell.linger);
+        }
     }
+    block
+}
+
+// What display_map last put in each character of the screen, so characters
+// that still look the same needn't be drawn again.  Anything drawn over the
+// map afterwards has to forget() the characters it covers, so they're redrawn
+// next frame.
+struct Shadow {
+    drawn: Vec<Option<(char, Color, Color)>>
+}
 
-    fn dec_linger(&mut self, x: usize, y: usize) {
-        if self.map[x][y].linger > 0 { self.map[x][y].linger -= 1; }
+impl Shadow {
+    fn new() -> Shadow {
+        Shadow { drawn: vec![None; (SCREEN_WIDTH * SCREEN_HEIGHT) as usize] }
     }
-    
-    // debug function
-    #[allow(dead_code)]
-    fn live_cells(&self) -> i32 {
-        self.map.iter().flat_map(|r| r.iter())
-            .filter(|cell| cell.alive == true)
-            .count() as i32
+
+    fn put(&mut self, root: &mut dyn Console, x: i32, y: i32, c: char, fg: Color, bg: Color) {
+        let i = (y * SCREEN_WIDTH + x) as usize;
+        if self.drawn[i] != Some((c, fg, bg)) {
+            root.put_char_ex(x, y, c, fg, bg);
+            self.drawn[i] = Some((c, fg, bg));
+        }
     }
 
-    fn live_neighbours(&self, x: usize, y: usize) -> i32 {
-        let mut count = 0;
-        let h = self.height - 1;
-        let w = self.width - 1;
-            
-        let li = if x == 0 { 0 } else { x - 1 };
-        let lj = if y == 0 { 0 } else { y - 1 };
-        let hi = if x == w { w } else { x + 1 };
-        let hj = if y == h { h } else { y + 1 };
-        for i in li..(hi + 1) {
-            for j in lj..(hj + 1) {
-                if i == x && j == y {
-                    continue;
-                } else {
-                    if self.map[i][j].alive { count += 1 };
-                }
+    // The characters in columns x0 to x1 and rows y0 to y1, clipped to the
+    // screen
+    fn forget(&mut self, x0: i32, y0: i32, x1: i32, y1: i32) {
+        for y in y0.max(0)..(y1 + 1).min(SCREEN_HEIGHT) {
+            for x in x0.max(0)..(x1 + 1).min(SCREEN_WIDTH) {
+                self.drawn[(y * SCREEN_WIDTH + x) as usize] = None;
             }
         }
-        count
     }
 
-    fn live_die(&mut self, x: usize, y: usize) -> bool {
-        // Is it alive?
-        let n = self.live_neighbours(x, y);
-        if self.map[x][y].alive {
-            // Check to see if it dies
-            if n > 3 || n < 2 {
-                self.flip_one(x, y, true)
-            } else { false }
+    fn forget_all(&mut self) {
+        self.forget(0, 0, SCREEN_WIDTH - 1, SCREEN_HEIGHT - 1);
+    }
+}
+
+fn display_map(root: &mut dyn Console, shadow: &mut Shadow, map: &Map, palette: &Palette, style: Style) {
+    if tiling(map, &style) {
+        shadow.forget_all();
+        return display_tiled(root, map, palette, style);
+    }
+    let (w, h) = (map.width as i32, map.height as i32);
+    let hex = map.neighbourhood == Neighbourhood::Hex;
+    let n = if hex { 1 } else { style.zoom };
+    // Whether the block at (i, j) touches an edge that wraps
+    let on_seam = |i: i32, j: i32| style.seams && match map.boundary {
+        BoundaryMode::Wrap => i == 0 || i + n >= w || j == 0 || j + n >= h,
+        BoundaryMode::CylinderX => i == 0 || i + n >= w,
+        BoundaryMode::Dead | BoundaryMode::Mirror => false
+    };
+    // Glyph, foreground and background for the block at map position (i, j),
+    // and the colour it's shown in as half a character
+    let look = |i: i32, j: i32| {
+        if i < 0 || j < 0 || i >= w || j >= h {
+            return (' ', color::WHITE, color::BLACK, color::BLACK);
+        }
+        let cell = &block_cell(map, i as usize, j as usize, n as usize);
+        let (c, shade) = cell_glyph(map, cell);
+        // cell_glyph uses the default characters, so swap in the chosen ones
+        let c = match c {
+            '*' => style.glyphs.0,
+            ' ' => style.glyphs.1,
+            c => c
+        };
+        let (c, fg, bg) = if style.crisp {
+            (if cell.alive { style.glyphs.0 } else { style.glyphs.1 }, color::WHITE, color::BLACK)
+        } else if style.by_age && cell.alive {
+            (c, color::WHITE, palette.shade(age_shade(cell.alive_for)))
         } else {
-            // It's dead.  Does it live?
-            if n == 3 {
-                self.flip_one(x, y, true)
-            } else { false }
-        }
-    }
-            
-    fn flip_one(&mut self, x: usize, y: usize, flip: bool) -> bool {
-        self.map[x][y].flip = flip;
-        flip
-    }
-                
-
-    fn flip_all(&mut self) {
-        for x in 0..(self.width - 1) {
-            for y in 0..(self.height - 1) {
-                let mut cell = &mut self.map[x as usize][y as usize];
-                if cell.flip {
-                    cell.alive = !cell.alive;
-                    cell.flip = false;
-                }
+            (c, color::WHITE, palette.shade(shade))
+        };
+        let bg = if style.changes && block_changed(map, i as usize, j as usize, n as usize) {
+            if cell.alive { BORN_COLOR } else { DIED_COLOR }
+        } else {
+            bg
+        };
+        let bg = if on_seam(i, j) { lighten(bg, SEAM_COLOR) } else { bg };
+        // Crisp half blocks show live cells in the foreground colour
+        let solid = if style.crisp && cell.alive { fg } else { bg };
+        (c, fg, bg, solid)
+    };
+    for x in 0..SCREEN_WIDTH {
+        for y in 0..SCREEN_HEIGHT {
+            if hex {
+                // Each cell takes a glyph and a blank; the half cell odd rows
+                // are shifted by leaves a gap at the left edge
+                let (i, j) = hex_cell(map, x, y);
+                let i = if i < map.o_x { -1 } else { i };
+                let (c, fg, bg, _) = look(i, j);
+                let c = if (x - (j & 1)) % 2 == 0 { c } else { ' ' };
+                shadow.put(root, x, y, c, fg, bg);
+            } else if style.halfblock {
+                let i = x * n + map.o_x;
+                let j = 2 * y * n + map.o_y;
+                let (_, _, _, top) = look(i, j);
+                let (_, _, _, bottom) = look(i, j + n);
+                shadow.put(root, x, y, UPPER_HALF_BLOCK, top, bottom);
+            } else {
+                let (c, fg, bg, _) = look(x * n + map.o_x, y * n + map.o_y);
+                shadow.put(root, x, y, c, fg, bg);
             }
         }
     }
+}
 
-    fn init_noise(&mut self) {
-        let noise2d = noise::Noise::init_with_dimensions(2).init();
-        let mut p: [f32; 2] = [ 0.0, 0.0 ];
-        for x in 0..(self.width - 1) {
-            for y in 0..(self.height - 1) {
-                p[0] = (x as f32 * NOISE_HORI) / self.width as f32;
-                p[1] = (y as f32 * NOISE_VERT) / self.height as f32;
-                let noise = noise2d.get_ex(p, noise::NoiseType::Perlin);
-                if noise >= 0.0 { self.map[x as usize][y as usize].alive = true };
-            }
+// The whole map tiled 3 x 3 and squeezed into the screen, so patterns
+// crossing an edge can be seen carrying on into the next tile.  Each
+// character stands for the block of the tiling under it and is lit if any
+// cell in the block is alive.  The middle tile is tinted.
+fn display_tiled(root: &mut dyn Console, map: &Map, palette: &Palette, style: Style) {
+    let (w, h) = (map.width, map.height);
+    let (cols, rows) = (SCREEN_WIDTH as usize, SCREEN_HEIGHT as usize);
+    for y in 0..rows {
+        let (j0, j1) = (y * 3 * h / rows, ((y + 1) * 3 * h / rows).max(y * 3 * h / rows + 1));
+        for x in 0..cols {
+            let (i0, i1) = (x * 3 * w / cols, ((x + 1) * 3 * w / cols).max(x * 3 * w / cols + 1));
+            let alive = (j0..j1).any(|j| (i0..i1).any(|i| map.cell(i % w, j % h).alive));
+            let middle = (w..2 * w).contains(&i0) && (h..2 * h).contains(&j0);
+            let bg = if middle { SEAM_COLOR } else { color::BLACK };
+            let (c, bg) = if alive { (style.glyphs.0, lighten(bg, palette.shade(9))) } else { (style.glyphs.1, bg) };
+            root.put_char_ex(x as i32, y as i32, c, color::WHITE, bg);
         }
     }
+}
 
-    fn toggle(&mut self, x: i32, y: i32) {
-        let i = x as usize;
-        let j = y as usize;
-        self.map[i][j].alive = !self.map[i][j].alive;
+// Mark the keyboard cursor at map position (x, y), if it's on screen, by
+// inverting its character.  In half block mode only its half of the
+// character is marked.
+fn display_cursor(root: &mut dyn Console, shadow: &mut Shadow, map: &Map, style: Style, (x, y): (i32, i32)) {
+    let (dx, dy) = (x - map.o_x, y - map.o_y);
+    if dx < 0 || dy < 0 { return };
+    let (screen_x, screen_y, width) = if map.neighbourhood == Neighbourhood::Hex {
+        (2 * dx + (y & 1), dy, 2)
+    } else if style.halfblock {
+        (dx / style.zoom, dy / style.zoom / 2, 1)
+    } else {
+        (dx / style.zoom, dy / style.zoom, 1)
+    };
+    if screen_y >= SCREEN_HEIGHT { return };
+    shadow.forget(screen_x, screen_y, screen_x + width - 1, screen_y);
+    for sx in (screen_x..screen_x + width).filter(|&sx| sx < SCREEN_WIDTH) {
+        if style.halfblock && map.neighbourhood != Neighbourhood::Hex {
+            if (dy / style.zoom) & 1 == 0 {
+                root.set_char_foreground(sx, screen_y, CURSOR_COLOR);
+            } else {
+                root.set_char_background(sx, screen_y, CURSOR_COLOR, BackgroundFlag::Set);
+            }
+        } else {
+            let (fg, bg) = (root.get_char_foreground(sx, screen_y), root.get_char_background(sx, screen_y));
+            root.set_char_foreground(sx, screen_y, bg);
+            root.set_char_background(sx, screen_y, fg, BackgroundFlag::Set);
+        }
     }
+}
 
-    fn tick(&mut self) {
-        // flip cells depending on the rules
-        for i in 0..self.width - 1 {
-            for j in 0..self.height - 1 {
-                self.live_die(i, j);
-            }
+// Outline the selection with corners at map positions a and b, clipped to
+// the screen
+fn display_selection(root: &mut dyn Console, shadow: &mut Shadow, map: &Map, style: Style, a: (i32, i32),
+                     b: (i32, i32)) {
+    let to_screen = |(x, y): (i32, i32)| {
+        let (dx, dy) = (x - map.o_x, y - map.o_y);
+        if map.neighbourhood == Neighbourhood::Hex {
+            (2 * dx + (y & 1), dy)
+        } else if style.halfblock {
+            (dx.div_euclid(style.zoom), dy.div_euclid(style.zoom * 2))
+        } else {
+            (dx.div_euclid(style.zoom), dy.div_euclid(style.zoom))
         }
-        // Cascade the flips into live/dead cells.  The reason we toggle a flip
-        // flag before this point is that we don't want cells toggled earlier
-        // in the array to affect cells further in the array.
-        self.flip_all();
-        // Update linger values.  Live cells brighten, dead cells fade.
-        for i in 0..self.width - 1 {
-            for j in 0..self.height - 1 {
-                if self.map[i][j].alive {
-                    // Grow to a maximum of 9
-                    self.inc_linger(i, j);
-                } else {
-                    // Fade to a minimum of 0
-                    self.dec_linger(i, j);
-                }
+    };
+    let ((ax, ay), (bx, by)) = (to_screen(a), to_screen(b));
+    let (left, right, top, bottom) = (ax.min(bx), ax.max(bx), ay.min(by), ay.max(by));
+    shadow.forget(left, top, right, bottom);
+    for sy in top.max(0)..(bottom + 1).min(SCREEN_HEIGHT) {
+        for sx in left.max(0)..(right + 1).min(SCREEN_WIDTH) {
+            if sx == left || sx == right || sy == top || sy == bottom {
+                root.set_char_background(sx, sy, SELECTION_COLOR, BackgroundFlag::Set);
             }
         }
     }
 }
 
+fn lighten(a: Color, b: Color) -> Color {
+    Color { r: a.r.max(b.r), g: a.g.max(b.g), b: a.b.max(b.b) }
+}
 
-fn display_map(root: &mut Console, map: &Map) {
-    let color_scale = [
-        color::BLACK,
-        color::DARKEST_RED,
-        color::DARKER_RED,
-        color::DARK_RED,
-        color::RED,
-        color::FLAME,
-        color::ORANGE,
-        color::AMBER,
-        color::YELLOW,
-        color::LIGHT_YELLOW
-    ];
-    for x in 0..SCREEN_WIDTH {
-        for y in 0..SCREEN_HEIGHT {
-            let cell = &map.map[(x + map.o_x) as usize][(y + map.o_y) as usize];
-            let c = if cell.alive { '*' } else { ' ' };
-            root.put_char_ex(x, y, c, color::WHITE, color_scale[cell.linger as usize]);
-        }
+// Status line drawn over the top row of the map
+fn display_hud(root: &mut RootConsole, map: &Map, tick_rate: u32, sim_rate: f64, message: &str,
+               edge_warning: bool, modes: &[&str]) {
+    let rule = map.rule.preset_name().map_or_else(|| map.rule.to_string(), |name| name.to_string());
+    let mut status = format!(" gen {}  pop {}  changed {}  {} tps  sim: {:.0} gen/s  {}  {} ",
+                             map.generation, map.live_cells(), map.changed_count(), tick_rate, sim_rate, rule,
+                             boundary_name(map.boundary));
+    // Where the live cells are, to help find them on a big map
+    if let Some((x0, y0, x1, y1)) = map.bounding_box() {
+        status.push_str(&format!(" box ({}, {}) {}x{} ", x0, y0, x1 - x0 + 1, y1 - y0 + 1));
+    }
+    // e.g. the brush and whether the view is following the cells
+    for mode in modes {
+        status.push_str(&format!(" {} ", mode));
+    }
+    if !message.is_empty() {
+        status.push_str(&format!(" {} ", message));
+    }
+    root.set_default_background(color::BLACK);
+    root.set_default_foreground(color::WHITE);
+    root.print_ex(0, 0, BackgroundFlag::Set, TextAlignment::Left, &status);
+    if edge_warning {
+        root.set_default_background(color::RED);
+        root.print_ex(status.len() as i32, 0, BackgroundFlag::Set, TextAlignment::Left, " Edge reached ");
+    }
+}
+
+// Generations per second actually achieved over the last SIM_RATE_WINDOW,
+// forgetting ticks older than that
+fn sim_rate(tick_times: &mut VecDeque<Instant>) -> f64 {
+    while tick_times.front().is_some_and(|t| t.elapsed() > SIM_RATE_WINDOW) {
+        tick_times.pop_front();
+    }
+    match (tick_times.front(), tick_times.back()) {
+        (Some(first), Some(last)) if last > first =>
+            (tick_times.len() - 1) as f64 / (*last - *first).as_secs_f64(),
+        _ => 0.0
+    }
+}
+
+// The command being typed, across the bottom row
+fn display_command_bar(root: &mut RootConsole, text: &str, error: Option<&str>) {
+    let line = format!(":{}_", text);
+    root.set_default_background(color::BLACK);
+    root.set_default_foreground(color::WHITE);
+    root.print_ex(0, SCREEN_HEIGHT - 1, BackgroundFlag::Set, TextAlignment::Left,
+                  format!("{:<1$}", line, SCREEN_WIDTH as usize));
+    // The last command's error, after what's typed, until it's fixed
+    if let Some(error) = error {
+        root.set_default_foreground(color::RED);
+        root.print_ex(SCREEN_WIDTH - 1, SCREEN_HEIGHT - 1, BackgroundFlag::Set, TextAlignment::Right, error);
+    }
+}
+
+// Type a key into a prompt: Backspace deletes, printable characters are
+// added, and spaces too if `spaces` is set
+fn edit_line(text: &mut String, key_state: &input::Key, spaces: bool) {
+    if key_state.code == input::KeyCode::Backspace {
+        text.pop();
+    } else if key_state.printable.is_ascii_graphic() || (spaces && key_state.printable == ' ') {
+        text.push(key_state.printable);
+    }
+}
+
+// Switch the map to `rule`, keeping the odds it had, which aren't part of
+// the notation.  Returns the message to show.
+fn set_rule(map: &mut Map, rule: Ruleset) -> String {
+    info!("rule changed to {} at generation {}", rule, map.generation);
+    map.set_rule(rule);
+    match map.rule.preset_name() {
+        Some(name) => format!("Rule {} ({})", name, map.rule),
+        None => format!("Rule {}", map.rule)
+    }
+}
+
+// Tick forwards to generation `target`, or step back to it as far as the
+// saved generations reach.  Returns the message to show.
+fn goto(map: &mut Map, target: u64) -> Result<String, String> {
+    if target > map.generation.saturating_add(MAX_GOTO_AHEAD) {
+        return Err(format!("can't run more than {} generations ahead at once", MAX_GOTO_AHEAD));
+    }
+    map.snapshot();
+    while map.generation > target && map.step_back() {}
+    while map.generation < target {
+        map.tick();
+    }
+    if map.generation == target {
+        Ok(format!("Generation {}", target))
+    } else {
+        Ok(format!("Can't rewind past generation {}", map.generation))
+    }
+}
+
+// Stamp a pattern centred on map position (x, y)
+fn stamp_at(map: &mut Map, rle: &str, x: i32, y: i32, orientation: Orientation) -> Result<(), String> {
+    map.stamp_around(&Pattern::from_rle(rle)?.oriented(orientation), x, y)
+}
+
+// The side of the map closest to (x, y), and how far along it (x, y) is
+fn nearest_edge(map: &Map, x: i32, y: i32) -> (Edge, usize) {
+    let x = x.clamp(0, map.width as i32 - 1) as usize;
+    let y = y.clamp(0, map.height as i32 - 1) as usize;
+    let sides = [(x, Edge::Left, y), (map.width - 1 - x, Edge::Right, y), (y, Edge::Top, x),
+                 (map.height - 1 - y, Edge::Bottom, x)];
+    let &(_, edge, at) = sides.iter().min_by_key(|side| side.0).unwrap();
+    (edge, at)
+}
+
+// What the status line should say after a generation that changed something
+fn tick_message(map: &Map) -> &'static str {
+    match map.period() {
+        Some(2) => "Oscillating (p2)",
+        _ => ""
     }
 }
 
+// Whether every cell has died.  Under a B0 rule an empty map can still be
+// a board full of life.
+fn extinct(map: &Map) -> bool {
+    map.live_cells() == 0 && !map.background()
+}
+
+fn extinct_message(map: &Map) -> String {
+    format!("Extinct at generation {}, r to reseed", map.generation)
+}
+
 fn main() {
 
-    let mut map = Map::new();
-    map.init_noise();
-    
+    if env::args().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+    let config = match Config::load(Path::new(CONFIG_FILE)) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("conway-rs: {}", e);
+            process::exit(1);
+        }
+    };
+    let opts = match Options::parse(env::args().skip(1), &config) {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("conway-rs: {}\n\n{}", e, USAGE);
+            process::exit(1);
+        }
+    };
+
+    // Quiet unless asked, in which case RUST_LOG can still pick the level
+    if opts.verbose {
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    }
+    info!("starting a {}x{} map with rule {}", opts.width, opts.height, opts.rule);
+
+    let mut map = Map::new(opts.width, opts.height, opts.rule.clone());
+    map.kind = opts.kind;
+    map.boundary = opts.boundary;
+    map.neighbourhood = opts.neighbourhood;
+    map.radius = opts.radius;
+    map.noise_scale_x = opts.noise_scale_x;
+    map.noise_scale_y = opts.noise_scale_y;
+    map.noise_threshold = opts.noise_threshold;
+    map.noise_octaves = opts.noise_octaves;
+    map.noise_persistence = opts.noise_persistence;
+    map.rewind_depth = opts.rewind_depth;
+    map.trail_length = opts.trail_length;
+    map.margin = opts.margin;
+    // Trails and seams are toggled with t and w, glyphs cycled with g, and the
+    // mouse wheel zooms
+    let mut style = Style { crisp: false, halfblock: opts.halfblock, seams: true, zoom: 1, glyphs: opts.glyphs,
+                           by_age: false, changes: false, tiled: false };
+    let (view_w, view_h) = view_size(&map, &style);
+    map.center_view(view_w, view_h);
+    #[cfg(feature = "rayon")]
+    {
+        if opts.jobs > 1 {
+            rayon::ThreadPoolBuilder::new().num_threads(opts.jobs).build_global()
+                .expect("failed to start the rayon thread pool");
+            map.parallel = true;
+        }
+    }
+
+    let mut seed = opts.seed;
+    if let Err(e) = seed_board(&mut map, &opts, seed) {
+        eprintln!("conway-rs: {}", e);
+        process::exit(1);
+    }
+    let mut soup_seed = seed.unwrap_or_else(time_seed);
+    map.set_rng_seed(soup_seed);
+    if opts.headless {
+        let sparse = match opts.backend {
+            Backend::Dense => None,
+            Backend::Sparse => Some(SparseMap::from_map(&map).unwrap_or_else(|e| {
+                eprintln!("conway-rs: {}", e);
+                process::exit(1);
+            }))
+        };
+        let survived = run_headless(&mut map, sparse, opts.generations, opts.print_every);
+        if let Some(ref path) = opts.dump_rle {
+            if let Err(e) = write_atomically(path, &map.to_rle()) {
+                eprintln!("conway-rs: {}", e);
+                process::exit(1);
+            }
+        }
+        if !survived {
+            process::exit(2);
+        }
+        return;
+    }
+
     // Initialize tcod
-    let mut root = RootConsole::initializer()
-        .size(SCREEN_WIDTH, SCREEN_HEIGHT)
-        .title("conway-rs")
-        .font("BrogueFont3.png", FontLayout::AsciiInRow)
-        .font_type(FontType::Greyscale)
-        .renderer(Renderer::SDL)
-        .init();
+    let mut root = match init_console(&opts) {
+        Ok(root) => root,
+        Err(e) => {
+            eprintln!("conway-rs: {}", e);
+            process::exit(1);
+        }
+    };
 
     // Clamp FPS
-    system::set_fps(FPS);
+    system::set_fps(opts.fps);
 
     // Declare game loop variables;
     let mut game_state = GameState::Initializing;
-    let frame_time = Duration::from_millis(1000 / (FPS as u64));
-    
+    let frame_time = Duration::from_millis(1000 / (opts.fps as u64));
+    // The simulation runs at its own rate, independent of rendering, so
+    // slowing it down doesn't make input sluggish
+    let mut tick_rate = (opts.fps as u32).clamp(MIN_TICK_RATE, MAX_TICK_RATE);
+    let mut tick_debt = Duration::from_secs(0);
+    let mut last_frame = Instant::now();
+    // When recent ticks happened, to show how fast the simulation really runs
+    let mut tick_times: VecDeque<Instant> = VecDeque::new();
+    // Whether the board has grown into an edge that doesn't wrap, as of the
+    // last tick
+    let mut touching_border = map.touches_border();
+    let started = Instant::now();
+    // Where new patterns get stamped: the last cell under the mouse, or the
+    // middle of the view until the mouse moves
+    let mut cursor: Option<(i32, i32)> = None;
+    // Whether the cursor was last moved with hjkl and should be shown
+    let mut keyboard_cursor = false;
+    // With y, the right button selects an area instead of erasing, and F2
+    // saves the live cells in it.  The selection's corners are map positions,
+    // the first where the drag started.
+    let mut selecting = false;
+    let mut selection: Option<((i32, i32), (i32, i32))> = None;
+    let mut dragging_selection = false;
+    // The rule being typed after pressing u
+    let mut rule_prompt: Option<String> = None;
+    // The command being typed after pressing :
+    let mut command_bar: Option<String> = None;
+    let mut command_error: Option<String> = None;
+    // Whether the view drifts after the live cells as they move, toggled with f
+    let mut following = false;
+    // How the patterns on 1 - 4 are turned, cycled with o
+    let mut orientation = Orientation::R0;
+    // The cell last drawn or erased during the current drag, and which
+    let mut last_painted: Option<(i32, i32, bool)> = None;
+    let mut brush = Brush::Draw;
+    // Shown in the status line, e.g. once the board has stabilized
+    let mut message = String::new();
+    // The GIF being recorded, opened the first time recording is switched on
+    let mut recorder: Option<export::Recorder> = None;
+    let mut recording = false;
+    let mut population = PopulationGraph::new();
+    let mut palette_idx = opts.palette;
+    let mut palette = PALETTES[palette_idx].1;
+    // Cycling away from a palette loaded from a file starts at the first
+    // built-in
+    if let Some(custom) = opts.custom_palette {
+        palette_idx = PALETTES.len() - 1;
+        palette = custom;
+    }
+
+    // Set when the last frame went over --max-frame-ms
+    let mut skip_render = false;
+    let mut shadow = Shadow::new();
 
     // Main loop
     while game_state != GameState::Ending && !root.window_closed() {
 
         let start_time = Instant::now();
         
-        display_map(&mut root, &map);
-        root.flush();
+        if !skip_render {
+            display_map(&mut root, &mut shadow, &map, &palette, style);
+            if let (true, Some(at), false) = (keyboard_cursor, cursor, tiling(&map, &style)) {
+                display_cursor(&mut root, &mut shadow, &map, style, at);
+            }
+            if let (Some((a, b)), false) = (selection, tiling(&map, &style)) {
+                display_selection(&mut root, &mut shadow, &map, style, a, b);
+            }
+            // Flash the warning on and off every EDGE_FLASH
+            let flash = touching_border && ((start_time - started).as_millis() / EDGE_FLASH.as_millis()) & 1 == 0;
+            // The rule being typed takes over the status line's message
+            let prompt;
+            let shown = match rule_prompt {
+                Some(ref text) => {
+                    prompt = format!("Rule: {}_", text);
+                    &prompt
+                },
+                None => &message
+            };
+            let mut modes = vec![brush.label()];
+            if following {
+                modes.push("FOLLOW");
+            }
+            display_hud(&mut root, &map, tick_rate, sim_rate(&mut tick_times), shown, flash, &modes);
+            population.draw(&mut root);
+            if let Some(ref text) = command_bar {
+                display_command_bar(&mut root, text, command_error.as_deref());
+            }
+            // The status line, graph and command bar cover the map
+            shadow.forget(0, 0, SCREEN_WIDTH - 1, 0);
+            shadow.forget(SCREEN_WIDTH - GRAPH_WIDTH, SCREEN_HEIGHT - GRAPH_HEIGHT,
+                          SCREEN_WIDTH - 1, SCREEN_HEIGHT - 1);
+            if command_bar.is_some() {
+                shadow.forget(0, SCREEN_HEIGHT - 1, SCREEN_WIDTH - 1, SCREEN_HEIGHT - 1);
+            }
+            root.flush();
+        }
+        let render_time = start_time.elapsed();
   
         match input::check_for_event(input::KEY | input::MOUSE) {
             None => {},
             Some((_, event)) => {
                 match event {
+                    // Typing a new rule: Enter applies it, Escape gives up
+                    input::Event::Key(ref key_state) if rule_prompt.is_some() => if key_state.pressed {
+                        match key_state.code {
+                            input::KeyCode::Enter => {
+                                let text = rule_prompt.take().unwrap_or_default();
+                                message = match Ruleset::parse(&text) {
+                                    Ok(rule) => set_rule(&mut map, rule),
+                                    Err(e) => e
+                                };
+                            },
+                            input::KeyCode::Escape => rule_prompt = None,
+                            _ => if let Some(ref mut text) = rule_prompt {
+                                edit_line(text, key_state, false);
+                            }
+                        }
+                    },
+                    // Typing a command, which runs on Enter
+                    input::Event::Key(ref key_state) if command_bar.is_some() => if key_state.pressed {
+                        match key_state.code {
+                            input::KeyCode::Enter => {
+                                let line = command_bar.clone().unwrap_or_default();
+                                let result = match Command::parse(&line) {
+                                    Ok(Command::Rule(rule)) => Ok(set_rule(&mut map, rule)),
+                                    Ok(Command::Seed(n)) => {
+                                        map.snapshot();
+                                        seed = Some(n);
+                                        seed_board(&mut map, &opts, seed).map(|()| format!("Seed {}", n))
+                                    },
+                                    Ok(Command::Goto(target)) => goto(&mut map, target),
+                                    Ok(Command::Save(path)) => write_atomically(&path, &map.to_rle())
+                                        .map(|()| format!("Saved {}", path.display())),
+                                    Err(e) => Err(e)
+                                };
+                                // Keep the bar open on an error, so the command can be fixed
+                                match result {
+                                    Ok(m) => {
+                                        message = m;
+                                        command_bar = None;
+                                        command_error = None;
+                                    },
+                                    Err(e) => command_error = Some(e)
+                                }
+                                touching_border = map.touches_border();
+                            },
+                            input::KeyCode::Escape => {
+                                command_bar = None;
+                                command_error = None;
+                            },
+                            _ => if let Some(ref mut text) = command_bar {
+                                let before = text.len();
+                                edit_line(text, key_state, true);
+                                if text.len() != before {
+                                    command_error = None;
+                                }
+                            }
+                        }
+                    },
                     input::Event::Key(ref key_state) => {
+                        // Start typing a new rule
+                        if key_state.code == input::KeyCode::Char && key_state.printable == 'u'
+                            && key_state.pressed {
+                            rule_prompt = Some(String::new());
+                        }
+                        // Open the command bar
+                        if key_state.code == input::KeyCode::Char && key_state.printable == ':'
+                            && key_state.pressed {
+                            command_bar = Some(String::new());
+                        }
                         if key_state.code == input::KeyCode::Enter && key_state.pressed {
+                            message.clear();
                             game_state = match game_state {
                                 GameState::Initializing => GameState::Running,
                                 GameState::Running => GameState::Initializing,
                                 GameState::Ending => GameState::Ending
                             };
-                        if key_state.code == input::KeyCode::Escape { game_state = GameState::Ending };
+                            let running = game_state == GameState::Running;
+                            info!("{} at generation {}", if running { "running" } else { "paused" }, map.generation);
+                        }
+                        if key_state.code == input::KeyCode::Escape && key_state.pressed {
+                            info!("quitting at generation {}", map.generation);
+                            game_state = GameState::Ending;
+                        }
+                        // Step a single generation while paused
+                        if key_state.code == input::KeyCode::Spacebar && key_state.pressed
+                            && game_state == GameState::Initializing {
+                            let changed = map.tick();
+                            message = if extinct(&map) {
+                                extinct_message(&map)
+                            } else {
+                                if changed { tick_message(&map) } else { "Stabilized" }.to_string()
+                            };
+                            population.push(map.live_cells());
+                            if recording {
+                                if let Some(e) = record_frame(&mut recorder, &map, tick_rate) {
+                                    message = e;
+                                    recording = false;
+                                }
+                            }
+                        }
+                        // Step back a generation while paused
+                        if key_state.code == input::KeyCode::Backspace && key_state.pressed
+                            && game_state == GameState::Initializing {
+                            message = if map.step_back() { "" } else { "No earlier generations" }.to_string();
+                        }
+                        // Wipe the board
+                        if key_state.code == input::KeyCode::Char && key_state.printable == 'c'
+                            && key_state.pressed {
+                            map.snapshot();
+                            map.clear();
+                            message.clear();
+                        }
+                        // Reseed the board, moving on to the next seed if we
+                        // were given one
+                        if key_state.code == input::KeyCode::Char && key_state.printable == 'r'
+                            && key_state.pressed {
+                            map.snapshot();
+                            seed = seed.map(|s| s.wrapping_add(1));
+                            debug!("reseeding at generation {}", map.generation);
+                            message = seed_board(&mut map, &opts, seed).err().unwrap_or_default();
+                        }
+                        // Thin out or thicken the noise seeding and reseed
+                        // from the same seed to compare
+                        if key_state.code == input::KeyCode::Char && key_state.pressed
+                            && (key_state.printable == '[' || key_state.printable == ']')
+                            && opts.init == InitKind::Noise && opts.text.is_none() {
+                            let step = if key_state.printable == ']' { 1.0 } else { -1.0 } * NOISE_THRESHOLD_STEP;
+                            map.snapshot();
+                            map.noise_threshold = (map.noise_threshold + step).clamp(-1.0, 1.0);
+                            // Noise seeding can't fail
+                            let _ = seed_board(&mut map, &opts, Some(*seed.get_or_insert_with(time_seed)));
+                            message = format!("Noise threshold {:.2}", map.noise_threshold);
+                        }
+                        // Swap live and dead cells
+                        if key_state.code == input::KeyCode::Char && key_state.printable == 'i'
+                            && key_state.pressed {
+                            map.snapshot();
+                            map.invert();
+                            message.clear();
+                        }
+                        if key_state.code == input::KeyCode::Char && key_state.printable == 'q'
+                            && key_state.pressed {
+                            brush = brush.next();
+                        }
+                        // Drop a soup into just the part of the board on
+                        // screen, again moving on from the seed each time
+                        if key_state.code == input::KeyCode::Char && key_state.printable == 's'
+                            && key_state.pressed {
+                            map.snapshot();
+                            soup_seed = soup_seed.wrapping_add(1);
+                            let (view_w, view_h) = view_size(&map, &style);
+                            let (x0, y0) = (map.o_x.max(0) as usize, map.o_y.max(0) as usize);
+                            map.soup(x0, y0, view_w as usize, view_h as usize, opts.density, soup_seed);
+                            message.clear();
+                        }
+                        // Scroll the viewport, unless it already shows
+                        // the whole map
+                        if key_state.pressed && !opts.fit {
+                            let step = if key_state.shift { FAST_PAN_STEP } else { PAN_STEP };
+                            let (dx, dy) = match key_state.code {
+                                input::KeyCode::Left => (-step, 0),
+                                input::KeyCode::Right => (step, 0),
+                                input::KeyCode::Up => (0, -step),
+                                input::KeyCode::Down => (0, step),
+                                _ => (0, 0)
+                            };
+                            let (view_w, view_h) = view_size(&map, &style);
+                            map.pan(dx, dy, view_w, view_h);
+                        }
+                        // Move the cursor with hjkl, a character at a time,
+                        // keeping it inside the view
+                        if key_state.pressed && key_state.code == input::KeyCode::Char {
+                            let step = if map.neighbourhood == Neighbourhood::Hex { 1 } else { style.zoom };
+                            let (dx, dy) = match key_state.printable {
+                                'h' => (-step, 0),
+                                'l' => (step, 0),
+                                'k' => (0, -step),
+                                'j' => (0, step),
+                                _ => (0, 0)
+                            };
+                            if (dx, dy) != (0, 0) {
+                                let (view_w, view_h) = view_size(&map, &style);
+                                let (x, y) = cursor.unwrap_or((map.o_x + view_w / 2, map.o_y + view_h / 2));
+                                let right = (map.o_x + view_w).min(map.width as i32) - 1;
+                                let bottom = (map.o_y + view_h).min(map.height as i32) - 1;
+                                cursor = Some(((x + dx).clamp(map.o_x, right), (y + dy).clamp(map.o_y, bottom)));
+                                keyboard_cursor = true;
+                            }
+                        }
+                        // Toggle the cell under the cursor
+                        if key_state.code == input::KeyCode::Char && key_state.printable == 'x'
+                            && key_state.pressed {
+                            if let Some((x, y)) = cursor {
+                                map.snapshot();
+                                map.toggle(x, y);
+                            }
+                        }
+                        // Stamp one of the built-in patterns
+                        if key_state.pressed {
+                            let rle = match key_state.code {
+                                input::KeyCode::Number1 => Some(patterns::GLIDER),
+                                input::KeyCode::Number2 => Some(patterns::LWSS),
+                                input::KeyCode::Number3 => Some(patterns::GOSPER_GLIDER_GUN),
+                                input::KeyCode::Number4 => Some(patterns::PULSAR),
+                                _ => None
+                            };
+                            if let Some(rle) = rle {
+                                map.snapshot();
+                                let stamped = match cursor {
+                                    Some((x, y)) => stamp_at(&mut map, rle, x, y, orientation),
+                                    None => {
+                                        let (view_w, view_h) = view_size(&map, &style);
+                                        map.stamp_centered(rle, view_w, view_h, orientation)
+                                    }
+                                };
+                                if let Err(e) = stamped {
+                                    message = e;
+                                }
+                            }
+                        }
+                        // Fire a glider in from the edge nearest the cursor
+                        if key_state.code == input::KeyCode::Char && key_state.printable == 'n'
+                            && key_state.pressed {
+                            let (view_w, view_h) = view_size(&map, &style);
+                            let (x, y) = cursor.unwrap_or((map.o_x + view_w / 2, map.o_y + view_h / 2));
+                            let (edge, at) = nearest_edge(&map, x, y);
+                            map.snapshot();
+                            if let Err(e) = map.inject_glider(edge, at) {
+                                message = e;
+                            }
+                        }
+                        // Switch to the next kind of edge, from the next tick on
+                        if key_state.code == input::KeyCode::Char && key_state.printable == 'e'
+                            && key_state.pressed {
+                            map.boundary = map.boundary.next();
+                            touching_border = map.touches_border();
+                        }
+                        // Bring the live cells into view
+                        if key_state.code == input::KeyCode::Char && key_state.printable == 'b'
+                            && key_state.pressed {
+                            if let Some((x0, y0, x1, y1)) = map.bounding_box() {
+                                let (view_w, view_h) = view_size(&map, &style);
+                                map.center_view_on(((x0 + x1) / 2) as i32, ((y0 + y1) / 2) as i32, view_w, view_h);
+                            }
+                        }
+                        // Keep the view on the live cells as they move
+                        if key_state.code == input::KeyCode::Char && key_state.printable == 'f'
+                            && key_state.pressed {
+                            following = !following;
+                        }
+                        // Turn the next stamps another way
+                        if key_state.code == input::KeyCode::Char && key_state.printable == 'o'
+                            && key_state.pressed {
+                            orientation = orientation.next();
+                            message = format!("Stamping {:?}", orientation);
+                        }
+                        // Save the whole board as a PNG
+                        if key_state.code == input::KeyCode::Char && key_state.printable == 'p'
+                            && key_state.pressed {
+                            let path = opts.screenshot_dir
+                                .join(format!("conway-gen-{:06}.png", map.generation));
+                            message = match export::save_png(&map, &path, opts.export_aspect, |c| palette.shade(cell_glyph(&map, c).1)) {
+                                Ok(()) => {
+                                    info!("saved a screenshot to {}", path.display());
+                                    format!("Saved {}", path.display())
+                                },
+                                Err(e) => e
+                            };
+                        }
+                        // Select areas with the right button instead of erasing
+                        if key_state.code == input::KeyCode::Char && key_state.printable == 'y'
+                            && key_state.pressed {
+                            selecting = !selecting;
+                            if !selecting {
+                                selection = None;
+                            }
+                            message = if selecting { "Right-drag to select, F2 to save" } else { "" }.to_string();
+                        }
+                        // Save what's in the selection as RLE
+                        if key_state.code == input::KeyCode::F2 && key_state.pressed {
+                            message = match selection {
+                                None => "Select an area first: press y and right-drag".to_string(),
+                                Some(((ax, ay), (bx, by))) => {
+                                    let clamp = |v: i32, len: usize| v.clamp(0, len as i32 - 1) as usize;
+                                    let (x0, x1) = (clamp(ax.min(bx), map.width), clamp(ax.max(bx), map.width));
+                                    let (y0, y1) = (clamp(ay.min(by), map.height), clamp(ay.max(by), map.height));
+                                    let path = opts.screenshot_dir
+                                        .join(format!("conway-gen-{:06}.rle", map.generation));
+                                    match write_atomically(&path, &map.region_to_rle(x0, y0, x1, y1)) {
+                                        Ok(()) => {
+                                            info!("saved the selection to {}", path.display());
+                                            format!("Saved {}", path.display())
+                                        },
+                                        Err(e) => e
+                                    }
+                                }
+                            };
+                        }
+                        // Start or pause recording to the --record file
+                        if key_state.code == input::KeyCode::Char && key_state.printable == 'v'
+                            && key_state.pressed {
+                            match opts.record {
+                                None => message = "Start with --record <file> to record".to_string(),
+                                Some(_) if recorder.as_ref().is_some_and(|r| r.is_full()) =>
+                                    message = "Recording is full".to_string(),
+                                Some(ref path) => {
+                                    if recorder.is_none() {
+                                        match export::Recorder::create(path, &map, &palette.shades(),
+                                                                       opts.record_frames, opts.export_aspect) {
+                                            Ok(r) => recorder = Some(r),
+                                            Err(e) => message = e
+                                        }
+                                    }
+                                    if recorder.is_some() {
+                                        recording = !recording;
+                                        info!("recording {} at generation {}",
+                                              if recording { "started" } else { "paused" }, map.generation);
+                                        message = if recording { "Recording" } else { "Recording paused" }
+                                            .to_string();
+                                    }
+                                }
+                            }
+                        }
+                        // Take back the last edit, but only while paused
+                        if key_state.code == input::KeyCode::Char && key_state.printable == 'z'
+                            && key_state.ctrl && key_state.pressed
+                            && game_state == GameState::Initializing {
+                            message = if map.undo() { "Undone" } else { "Nothing to undo" }.to_string();
+                        }
+                        // Draw cells with the next of the GLYPHS
+                        if key_state.code == input::KeyCode::Char && key_state.printable == 'g'
+                            && key_state.pressed {
+                            let next = GLYPHS.iter().position(|&g| g == style.glyphs).map_or(0, |i| i + 1);
+                            style.glyphs = GLYPHS[next % GLYPHS.len()];
+                            message = format!("Glyphs: '{}' and '{}'", style.glyphs.0, style.glyphs.1);
+                        }
+                        // Show or hide the trails
+                        if key_state.code == input::KeyCode::Char && key_state.printable == 't'
+                            && key_state.pressed {
+                            style.crisp = !style.crisp;
+                            message = if style.crisp { "Trails off" } else { "Trails on" }.to_string();
+                        }
+                        // Shade cells by their age or by their trails
+                        if key_state.code == input::KeyCode::Char && key_state.printable == 'a'
+                            && key_state.pressed {
+                            style.by_age = !style.by_age;
+                            message = if style.by_age { "Shading by age" } else { "Shading by trails" }.to_string();
+                        }
+                        // Pick out or stop picking out what the last tick changed
+                        if key_state.code == input::KeyCode::Char && key_state.printable == 'd'
+                            && key_state.pressed {
+                            style.changes = !style.changes;
+                            message = if style.changes { "Showing changes" } else { "Hiding changes" }.to_string();
+                        }
+                        // Tile a wrapping map to see across its edges
+                        if key_state.code == input::KeyCode::Char && key_state.printable == 'm'
+                            && key_state.pressed {
+                            style.tiled = !style.tiled;
+                            message = match (style.tiled, map.boundary) {
+                                (false, _) => "Tiling off",
+                                (true, BoundaryMode::Wrap) => "Tiling on",
+                                (true, _) => "Tiling on, once the map wraps"
+                            }.to_string();
+                        }
+                        // Show or hide the edges that wrap
+                        if key_state.code == input::KeyCode::Char && key_state.printable == 'w'
+                            && key_state.pressed {
+                            style.seams = !style.seams;
+                            message = if style.seams { "Seams on" } else { "Seams off" }.to_string();
+                        }
+                        // Cycle through the built-in palettes, or with shift
+                        // the preset rules
+                        if key_state.code == input::KeyCode::Tab && key_state.pressed && key_state.shift {
+                            let next = map.rule.next_preset();
+                            message = set_rule(&mut map, next);
+                        } else if key_state.code == input::KeyCode::Tab && key_state.pressed {
+                            palette_idx = (palette_idx + 1) % PALETTES.len();
+                            palette = PALETTES[palette_idx].1;
+                            message = format!("Palette: {}", PALETTES[palette_idx].0);
+                        }
+                        // Save or restore the whole session
+                        if key_state.code == input::KeyCode::F5 && key_state.pressed {
+                            let name = PALETTES.iter().find(|&&(_, p)| p.colors == palette.colors)
+                                .map_or("custom", |&(name, _)| name);
+                            message = match map.save_session(&opts.session, name) {
+                                Ok(()) => format!("Saved {}", opts.session.display()),
+                                Err(e) => e
+                            };
+                        }
+                        if key_state.code == input::KeyCode::F9 && key_state.pressed {
+                            message = match restore_session(&opts, &style) {
+                                Ok((mut loaded, name)) => {
+                                    // Seeding isn't part of a session, so keep tuning it
+                                    loaded.noise_scale_x = map.noise_scale_x;
+                                    loaded.noise_scale_y = map.noise_scale_y;
+                                    loaded.noise_threshold = map.noise_threshold;
+                                    loaded.noise_octaves = map.noise_octaves;
+                                    loaded.noise_persistence = map.noise_persistence;
+                                    map = loaded;
+                                    // Palettes loaded from files aren't saved, so keep
+                                    // whatever is showing if the name isn't a built-in
+                                    if let Ok(i) = Palette::find(&name) {
+                                        palette_idx = i;
+                                        palette = PALETTES[i].1;
+                                    }
+                                    game_state = GameState::Initializing;
+                                    format!("Loaded {}", opts.session.display())
+                                },
+                                Err(e) => e
+                            };
+                        }
+                        // Speed up or slow down the simulation
+                        if key_state.pressed && (key_state.code == input::KeyCode::NumPadAdd
+                            || (key_state.code == input::KeyCode::Char
+                                && (key_state.printable == '+' || key_state.printable == '='))) {
+                            let step = if tick_rate < 10 { 1 } else { 5 };
+                            tick_rate = (tick_rate + step).min(MAX_TICK_RATE);
+                        }
+                        if key_state.pressed && (key_state.code == input::KeyCode::NumPadSubtract
+                            || (key_state.code == input::KeyCode::Char && key_state.printable == '-')) {
+                            let step = if tick_rate <= 10 { 1 } else { 5 };
+                            tick_rate = tick_rate.saturating_sub(step).max(MIN_TICK_RATE);
                         }
                     },
+                    // The tiled view's characters don't each stand for one cell
+                    input::Event::Mouse(_) if tiling(&map, &style) => {},
                     input::Event::Mouse(ref mouse_state) => {
-                        let x = mouse_state.cx as i32 + map.o_x;
-                        let y = mouse_state.cy as i32 + map.o_y;
-                        if mouse_state.lbutton_pressed { map.toggle(x, y) };                    }
+                        let (x, y) = if map.neighbourhood == Neighbourhood::Hex {
+                            hex_cell(&map, mouse_state.cx as i32, mouse_state.cy as i32)
+                        } else if opts.halfblock {
+                            // Which half of the character the pointer is in
+                            let (_, char_h) = system::get_char_size();
+                            let lower = (mouse_state.y as i32 % char_h) * 2 >= char_h;
+                            let row = mouse_state.cy as i32 * 2 + lower as i32;
+                            (mouse_state.cx as i32 * style.zoom + map.o_x, row * style.zoom + map.o_y)
+                        } else {
+                            (mouse_state.cx as i32 * style.zoom + map.o_x,
+                             mouse_state.cy as i32 * style.zoom + map.o_y)
+                        };
+                        if mouse_state.wheel_up { zoom_by(&mut map, &mut style, -1) };
+                        if mouse_state.wheel_down { zoom_by(&mut map, &mut style, 1) };
+                        cursor = Some((x, y));
+                        if mouse_state.dx != 0 || mouse_state.dy != 0 {
+                            keyboard_cursor = false;
+                        }
+                        // Hold the left button to draw and the right to erase, or
+                        // in selection mode to drag out the selection
+                        if selecting && mouse_state.rbutton {
+                            let start = match selection {
+                                Some((start, _)) if dragging_selection => start,
+                                _ => (x, y)
+                            };
+                            selection = Some((start, (x, y)));
+                            dragging_selection = true;
+                        } else if mouse_state.lbutton && brush != Brush::Draw {
+                            // The other brushes act once per click
+                            if last_painted.is_none() {
+                                map.snapshot();
+                                if brush == Brush::Toggle {
+                                    map.toggle(x, y);
+                                } else {
+                                    soup_seed = soup_seed.wrapping_add(1);
+                                    map.stamp_noise_blob(x, y, opts.blob_radius, opts.blob_density, soup_seed);
+                                }
+                                last_painted = Some((x, y, true));
+                            }
+                        } else if mouse_state.lbutton || mouse_state.rbutton {
+                            let alive = mouse_state.lbutton;
+                            // One undo step per drag
+                            if last_painted.is_none() { map.snapshot() };
+                            if last_painted != Some((x, y, alive)) {
+                                map.paint(x, y, alive);
+                                last_painted = Some((x, y, alive));
+                            }
+                        } else {
+                            last_painted = None;
+                            dragging_selection = false;
+                        }
+                    }
+                }
+            }
+        }
+        // Run however many ticks are due since the last frame.  If ticking
+        // can't keep up, drop the backlog rather than falling further behind.
+        let now = Instant::now();
+        if game_state == GameState::Running {
+            tick_debt += now - last_frame;
+            let interval = Duration::from_secs(1) / tick_rate;
+            let mut ticks = 0;
+            while tick_debt >= interval && ticks < MAX_TICK_RATE {
+                tick_debt -= interval;
+                ticks += 1;
+                // Nothing will change from here on, so stop and say so
+                let changed = map.tick();
+                tick_times.push_back(Instant::now());
+                touching_border = map.touches_border();
+                population.push(map.live_cells());
+                if following {
+                    let (view_w, view_h) = view_size(&map, &style);
+                    map.follow(view_w, view_h, FOLLOW_EASE);
+                }
+                // Stop too once everything has died, which soon settles
+                // but deserves its own message
+                if extinct(&map) {
+                    info!("extinct at generation {}", map.generation);
+                    message = extinct_message(&map);
+                    game_state = GameState::Initializing;
+                    break;
+                }
+                if !changed {
+                    debug!("pausing now that the board has stabilized");
+                    message = "Stabilized".to_string();
+                    game_state = GameState::Initializing;
+                    break;
+                }
+                message = tick_message(&map).to_string();
+                if recording {
+                    if let Some(e) = record_frame(&mut recorder, &map, tick_rate) {
+                        message = e;
+                        recording = false;
+                    }
+                }
+                // Pause at --stop-at; Enter carries on from there
+                if opts.stop_at == Some(map.generation) {
+                    info!("pausing at generation {} for --stop-at", map.generation);
+                    message = format!("Reached generation {}", map.generation);
+                    game_state = GameState::Initializing;
+                    break;
                 }
             }
+            if tick_debt >= interval { tick_debt = Duration::from_secs(0) };
+        } else {
+            tick_debt = Duration::from_secs(0);
         }
-        if game_state == GameState::Running { map.tick() }
+        last_frame = now;
+        // If drawing and ticking went over budget, skip drawing the next
+        // frame so input and ticks still get their turn
+        skip_render = !skip_render && opts.max_frame
+            .is_some_and(|budget| render_time + now.elapsed() > budget);
 
         // Wait until a full frame time has elapsed
         let time_diff = start_time.elapsed();
@@ -252,4 +1743,149 @@ fn main() {
             thread::sleep(frame_time - time_diff);
         }
     }
+
+    if let Some(recorder) = recorder {
+        if let Err(e) = recorder.finish() {
+            eprintln!("conway-rs: {}", e);
+        }
+    }
+}
+
+// Load the --session file, checking it fills the view and bringing back the
+// settings that aren't saved with it
+fn restore_session(opts: &Options, style: &Style) -> Result<(Map, String), String> {
+    let (mut map, palette) = Map::load_session(&opts.session)?;
+    let (view_w, view_h) = view_size(&map, &Style { zoom: 1, ..*style });
+    if map.width < view_w as usize || map.height < view_h as usize {
+        return Err(format!("{}x{} session is smaller than the view", map.width, map.height));
+    }
+    let (view_w, view_h) = view_size(&map, style);
+    map.pan(0, 0, view_w, view_h);
+    map.rewind_depth = opts.rewind_depth;
+    map.trail_length = opts.trail_length;
+    map.margin = opts.margin;
+    map.rule.birth_prob = opts.birth_chance;
+    map.rule.survival_prob = opts.survival_chance;
+    map.set_rng_seed(opts.seed.unwrap_or_else(time_seed));
+    #[cfg(feature = "rayon")]
+    {
+        map.parallel = opts.jobs > 1;
+    }
+    Ok((map, palette))
+}
+
+// Open the window.  libtcod has no way to report failure, so check for the
+// things that usually go wrong beforehand and that a console came up after.
+fn init_console(opts: &Options) -> Result<RootConsole, String> {
+    if !Path::new(&opts.font).is_file() {
+        return Err(format!("font not found: {}", opts.font));
+    }
+    let root = RootConsole::initializer()
+        .size(SCREEN_WIDTH, SCREEN_HEIGHT)
+        .title("conway-rs")
+        .font(&opts.font, opts.font_layout)
+        .font_type(FontType::Greyscale)
+        .renderer(opts.renderer)
+        .init();
+    if root.width() != SCREEN_WIDTH || root.height() != SCREEN_HEIGHT {
+        return Err("failed to start the renderer".to_string());
+    }
+    Ok(root)
+}
+
+// Fill the board the way --init asks, from `seed` if there is one.  Only
+// --init image can fail, if its file can't be read.
+fn seed_board(map: &mut Map, opts: &Options, seed: Option<u32>) -> Result<(), String> {
+    // Or write --text in the middle of the map, to watch it dissolve
+    if let Some(ref text) = opts.text {
+        map.clear();
+        let x = map.width.saturating_sub(text_width(text)) / 2;
+        let y = map.height.saturating_sub(GLYPH_HEIGHT) / 2;
+        map.stamp_text(text, x, y);
+        return Ok(());
+    }
+    match (opts.init, seed) {
+        (InitKind::Noise, Some(seed)) => map.init_noise_seeded(seed),
+        (InitKind::Noise, None) => map.init_noise(),
+        (InitKind::Random, _) => map.init_random(opts.density, seed.unwrap_or_else(time_seed)),
+        (InitKind::Symmetric, _) =>
+            map.init_symmetric_soup(opts.symmetry, opts.density, seed.unwrap_or_else(time_seed)),
+        (InitKind::Image, _) => if let Some(ref path) = opts.image {
+            map.init_from_image(path, opts.image_threshold)?
+        }
+    }
+    Ok(())
+}
+
+// Run up to `generations` ticks and print how the board ended up, returning
+// false if nothing is left alive.  With a SparseMap the ticks run on that
+// instead, and it's copied back into `map` whenever the board is needed.
+fn run_headless(map: &mut Map, mut sparse: Option<SparseMap>, generations: u64, print_every: Option<u64>) -> bool {
+    // Dump the board every so many generations, starting with the first
+    let due = |generation: u64| print_every.is_some_and(|n| generation.is_multiple_of(n));
+    let mut stabilized = false;
+    if due(map.generation) {
+        println!("generation {}:\n{}", map.generation, map.to_ascii());
+    }
+    for _ in 0..generations {
+        let changed = match sparse {
+            Some(ref mut sparse) => sparse.tick(),
+            None => map.tick()
+        };
+        if !changed {
+            stabilized = true;
+            break;
+        }
+        let generation = sparse.as_ref().map_or(map.generation, |s| s.generation);
+        if due(generation) {
+            if let Some(ref sparse) = sparse {
+                sparse.write_to(map);
+            }
+            println!("generation {}:\n{}", map.generation, map.to_ascii());
+        }
+    }
+    if let Some(ref sparse) = sparse {
+        sparse.write_to(map);
+    }
+    // Run on a copy for a glider's period to see what's escaping
+    let mut later = map.clone();
+    for _ in 0..GLIDER_PERIOD {
+        later.tick();
+    }
+    let population = map.live_cells();
+    println!("generations: {}", map.generation);
+    println!("population: {}", population);
+    println!("stabilized: {}", if stabilized { "yes" } else { "no" });
+    match sparse.as_ref().map_or(map.period(), |s| s.period()) {
+        Some(p) => println!("period: {}", p),
+        None => println!("period: none")
+    }
+    println!("gliders: {}", later.detect_spaceships(map));
+    population > 0
+}
+
+// Write `contents` to a temporary file next to `path` and rename it into
+// place, so nothing ever sees a half-written file
+fn write_atomically(path: &Path, contents: &str) -> Result<(), String> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    fs::write(&tmp, contents)
+        .and_then(|_| fs::rename(&tmp, path))
+        .map_err(|e| {
+            let _ = fs::remove_file(&tmp);
+            format!("couldn't write {}: {}", path.display(), e)
+        })
+}
+
+// Append the board to the recording, returning a message if recording has to
+// stop because it failed or ran out of frames
+fn record_frame(recorder: &mut Option<export::Recorder>, map: &Map, tick_rate: u32) -> Option<String> {
+    let r = recorder.as_mut()?;
+    // GIF frame delays are in hundredths of a second
+    let delay = (100 / tick_rate).max(1) as u16;
+    if let Err(e) = r.add_frame(map, delay, |c| cell_glyph(map, c).1) {
+        return Some(e);
+    }
+    if r.is_full() { Some("Recording is full".to_string()) } else { None }
 }
@@ -0,0 +1,1666 @@
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+#[cfg(feature = "session")]
+use serde::{Deserialize, Serialize};
+
+use std::collections::VecDeque;
+use std::mem;
+
+use noise::Perlin;
+use rng::{roll, time_seed, Rng};
+use rules::Ruleset;
+
+// Default map size
+pub const MAP_WIDTH: usize = 300;
+pub const MAP_HEIGHT: usize = 80;
+
+// Default noise scales, chosen purely because they look good: how many noise
+// cycles fit across and down the map
+pub const NOISE_VERT: f32 = 12.0;
+pub const NOISE_HORI: f32  = 40.0;
+
+// How many edits can be undone
+pub const UNDO_DEPTH: usize = 20;
+// How many past generations step_back() can return to by default
+pub const REWIND_DEPTH: usize = 100;
+// How many generations a dead cell's trail lasts by default
+pub const TRAIL_LENGTH: u8 = 9;
+// The widest neighbourhood radius.  Each tick counts (2r + 1)^2 cells around
+// every cell, so much beyond this and ticks crawl.
+pub const MAX_RADIUS: usize = 10;
+
+#[derive(Copy, Clone, Default)]
+pub struct Cell {
+    pub alive: bool,
+    pub linger: u8,
+    // Used by Brian's Brain and Generations rules: the cell is dying and
+    // can't be reborn yet
+    pub dying: bool,
+    // Generations rules only: how many ticks a dying cell has been dying,
+    // starting from 1
+    pub age: u8,
+    // How many generations in a row the cell has survived, 0 when it's newly
+    // born or dead.  Unlike linger this tells old cells from young ones.
+    pub alive_for: u16,
+    // Immigration only: which of the two sides, 0 or 1, the cell belongs to
+    pub color: u8
+}
+
+// Which automaton the map runs.  `Life` follows the map's B/S rule, while
+// `BriansBrain` has three states: an off cell turns on with exactly two on
+// neighbours, on cells start dying and dying cells turn off.  `Immigration`
+// is `Life` with two colours of cell, where newborn cells take the colour
+// most of their live neighbours have.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "session", derive(Serialize, Deserialize))]
+pub enum SimKind {
+    Life,
+    BriansBrain,
+    Immigration
+}
+
+// How neighbour counting treats cells past the edge of the map.  `Dead`
+// treats everything outside as dead, `Wrap` joins opposite edges into a torus
+// and `CylinderX` only joins the left and right edges.  `Mirror` reflects
+// about the edge cells, so the cell just past an edge (-1 or width) reads as
+// the one just inside it (1 or width - 2).
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "session", derive(Serialize, Deserialize))]
+pub enum BoundaryMode {
+    Dead,
+    Wrap,
+    CylinderX,
+    Mirror
+}
+
+impl BoundaryMode {
+    // The mode after this one, so a key can cycle through them all
+    pub fn next(self) -> BoundaryMode {
+        match self {
+            BoundaryMode::Dead => BoundaryMode::Wrap,
+            BoundaryMode::Wrap => BoundaryMode::CylinderX,
+            BoundaryMode::CylinderX => BoundaryMode::Mirror,
+            BoundaryMode::Mirror => BoundaryMode::Dead
+        }
+    }
+}
+
+// The symmetry init_symmetric_soup gives a board.  `MirrorX` reflects the
+// left half onto the right, `MirrorXY` reflects that across the middle row as
+// well, and `Rotate4` repeats a quarter turned four ways about the centre.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SymmetryKind {
+    None,
+    MirrorX,
+    MirrorXY,
+    Rotate4
+}
+
+// Which cells count as neighbours: all eight surrounding cells, or only the
+// four orthogonal ones.  Under `VonNeumann` rule counts only go up to 4.
+// With a radius above 1 these become every cell within that Chebyshev
+// (Moore) or Manhattan (Von Neumann) distance.
+//
+// `Hex` treats the board as a hex grid in offset coordinates, with odd rows
+// shifted half a cell right, giving each cell six neighbours.  It ignores
+// the radius.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "session", derive(Serialize, Deserialize))]
+pub enum Neighbourhood {
+    Moore,
+    VonNeumann,
+    Hex
+}
+
+// Neighbour offsets on the hex grid, for cells in even and odd rows
+const HEX_EVEN: [(i32, i32); 6] = [(-1, 0), (1, 0), (-1, -1), (0, -1), (-1, 1), (0, 1)];
+const HEX_ODD: [(i32, i32); 6] = [(-1, 0), (1, 0), (0, -1), (1, -1), (0, 1), (1, 1)];
+
+#[derive(Clone)]
+pub struct Map {
+    // The current generation in row-major order, see idx().  Each tick
+    // computes the next generation into `back` and swaps the two.
+    pub front: Vec<Cell>,
+    back: Vec<Cell>,
+    // Whether `back` still holds the generation before `front`, i.e. there's
+    // been a tick and no edit since
+    back_is_previous: bool,
+    // How many cells were born or died in the last tick
+    changed_count: usize,
+    pub height: usize,
+    pub width: usize,
+    pub o_x: i32,
+    pub o_y: i32,
+    pub rule: Ruleset,
+    pub boundary: BoundaryMode,
+    pub kind: SimKind,
+    pub neighbourhood: Neighbourhood,
+    // How far away a cell can be and still count as a neighbour
+    pub radius: usize,
+    // Seeds the random numbers stochastic rules use, see set_rng_seed()
+    rng_seed: u32,
+    // How init_noise seeds the board: the noise's scale across and down, and
+    // the value above which a cell starts alive.  Raising the threshold from
+    // 0 thins the board out.
+    pub noise_scale_x: f32,
+    pub noise_scale_y: f32,
+    pub noise_threshold: f32,
+    // Layers of ever finer noise added on top, each `noise_persistence`
+    // times as strong as the last, for more detailed boards.  1 gives
+    // smooth blobs.
+    pub noise_octaves: u32,
+    pub noise_persistence: f32,
+    // Number of ticks since the board was last seeded.  At one tick a
+    // nanosecond this would take centuries to overflow, but it wraps rather
+    // than panicking if it ever does.
+    pub generation: u64,
+    // Checksum of the generation before the previous one, and the period
+    // that comparing against it reveals for the current one
+    prev_checksum: Option<u64>,
+    period: Option<u32>,
+    // Boards saved by snapshot() for undo(), oldest first, packed one bit
+    // per cell
+    history: VecDeque<Vec<u64>>,
+    // The same for the generations before this one, saved by tick(), along
+    // with their generation numbers
+    past: VecDeque<(u64, Vec<u64>)>,
+    // Most generations `past` holds; 0 turns saving them off
+    pub rewind_depth: usize,
+    // Width of a band around the edge of the map that's kept dead: tick()
+    // never brings its cells to life, and they never count as neighbours
+    pub margin: usize,
+    // A cell's linger climbs to this while it lives and falls by one each
+    // generation after it dies, so its trail lasts this many generations
+    pub trail_length: u8,
+    // Work out each generation across the rayon thread pool
+    #[cfg(feature = "rayon")]
+    pub parallel: bool
+}
+
+
+// Reflect an index outside 0..len back inside it, bouncing off both ends
+// as many times as it takes
+fn reflect(i: i32, len: i32) -> i32 {
+    if len == 1 { return 0 };
+    let i = i.rem_euclid(2 * (len - 1));
+    if i >= len { 2 * (len - 1) - i } else { i }
+}
+
+// Where position (i, j) ends up on a width x height board with the given
+// edges, or None if it's off the board
+pub fn neighbour_of(boundary: BoundaryMode, width: usize, height: usize, i: i32, j: i32) -> Option<(usize, usize)> {
+    let (w, h) = (width as i32, height as i32);
+    match boundary {
+        BoundaryMode::Dead => {
+            if i < 0 || j < 0 || i >= w || j >= h { None }
+            else { Some((i as usize, j as usize)) }
+        },
+        BoundaryMode::Wrap => Some((i.rem_euclid(w) as usize, j.rem_euclid(h) as usize)),
+        BoundaryMode::CylinderX => {
+            if j < 0 || j >= h { None }
+            else { Some((i.rem_euclid(w) as usize, j as usize)) }
+        },
+        BoundaryMode::Mirror => Some((reflect(i, w) as usize, reflect(j, h) as usize))
+    }
+}
+
+impl Map {
+    pub fn new(width: usize, height: usize, rule: Ruleset) -> Map {
+        Map {
+            front: vec![Cell::default(); width * height],
+            back: vec![Cell::default(); width * height],
+            back_is_previous: false,
+            changed_count: 0,
+            height,
+            width,
+            o_x: 0,
+            o_y: 0,
+            rule,
+            boundary: BoundaryMode::Dead,
+            kind: SimKind::Life,
+            neighbourhood: Neighbourhood::Moore,
+            radius: 1,
+            rng_seed: 0,
+            noise_scale_x: NOISE_HORI,
+            noise_scale_y: NOISE_VERT,
+            noise_threshold: 0.0,
+            noise_octaves: 1,
+            noise_persistence: 0.5,
+            generation: 0,
+            prev_checksum: None,
+            period: None,
+            history: VecDeque::new(),
+            past: VecDeque::new(),
+            rewind_depth: REWIND_DEPTH,
+            margin: 0,
+            trail_length: TRAIL_LENGTH,
+            #[cfg(feature = "rayon")]
+            parallel: false
+        }
+    }
+
+    // Position of (x, y) in the cell buffer
+    pub fn idx(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
+    }
+
+    // Move a view_w x view_h viewport by (dx, dy), keeping it on the map
+    pub fn pan(&mut self, dx: i32, dy: i32, view_w: i32, view_h: i32) {
+        let max_x = (self.width as i32 - view_w).max(0);
+        let max_y = (self.height as i32 - view_h).max(0);
+        self.o_x = (self.o_x + dx).clamp(0, max_x);
+        self.o_y = (self.o_y + dy).clamp(0, max_y);
+    }
+
+    pub fn cell(&self, x: usize, y: usize) -> &Cell {
+        &self.front[self.idx(x, y)]
+    }
+
+    pub fn set_alive(&mut self, x: usize, y: usize, alive: bool) {
+        let i = self.idx(x, y);
+        self.front[i].alive = alive;
+        self.front[i].dying = false;
+        self.front[i].age = 0;
+        self.front[i].alive_for = 0;
+        self.back_is_previous = false;
+    }
+
+    // Whether the cell at (x, y) was born or died in the last tick.  Always
+    // false once the board has been edited since.
+    pub fn changed(&self, x: usize, y: usize) -> bool {
+        let i = self.idx(x, y);
+        self.back_is_previous && self.front[i].alive != self.back[i].alive
+    }
+
+    // How many cells changed() is true for: the Hamming distance between the
+    // last two generations.  Stays high while the board is chaotic and falls
+    // towards zero as it settles.
+    pub fn changed_count(&self) -> usize {
+        if self.back_is_previous { self.changed_count } else { 0 }
+    }
+
+    // Centre a view_w x view_h viewport on map position (x, y), as far as it
+    // can be without leaving the map
+    pub fn center_view_on(&mut self, x: i32, y: i32, view_w: i32, view_h: i32) {
+        self.o_x = x - view_w / 2;
+        self.o_y = y - view_h / 2;
+        self.pan(0, 0, view_w, view_h);
+    }
+
+    // Move the view `amount` of the way (0 - 1) towards being centred on the
+    // live cells' centre of mass, always by at least a cell so it doesn't
+    // stall just short.  Stays inside the map.
+    pub fn follow(&mut self, view_w: i32, view_h: i32, amount: f32) {
+        let (cx, cy) = match self.center_of_mass() {
+            Some(c) => c,
+            None => return
+        };
+        let step = |from: i32, to: f32| {
+            let d = (to - from as f32) * amount;
+            if d.abs() < 1.0 && (to - from as f32).abs() >= 1.0 { d.signum() as i32 } else { d as i32 }
+        };
+        let dx = step(self.o_x, cx - (view_w / 2) as f32);
+        let dy = step(self.o_y, cy - (view_h / 2) as f32);
+        self.pan(dx, dy, view_w, view_h);
+    }
+
+    // Centre a view_w x view_h viewport on the map
+    pub fn center_view(&mut self, view_w: i32, view_h: i32) {
+        self.o_x = (self.width as i32 - view_w) / 2;
+        self.o_y = (self.height as i32 - view_h) / 2;
+    }
+
+    // (x, y) of every live cell in row-major order: left to right along the
+    // top row, then on down the map
+    pub fn live_iter(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let width = self.width;
+        self.front.iter().enumerate()
+            .filter(|&(_, cell)| cell.alive)
+            .map(move |(i, _)| (i % width, i / width))
+    }
+
+    pub fn live_cells(&self) -> i32 {
+        self.live_iter().count() as i32
+    }
+
+    // Smallest (min_x, min_y, max_x, max_y) box containing every live cell,
+    // or None if the board is empty
+    pub fn bounding_box(&self) -> Option<(usize, usize, usize, usize)> {
+        self.live_iter().fold(None, |bbox, (x, y)| Some(match bbox {
+            None => (x, y, x, y),
+            Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y))
+        }))
+    }
+
+    // Average position of the live cells, or None if there are none
+    pub fn center_of_mass(&self) -> Option<(f32, f32)> {
+        let (n, sx, sy) = self.live_iter()
+            .fold((0usize, 0usize, 0usize), |(n, sx, sy), (x, y)| (n + 1, sx + x, sy + y));
+        if n == 0 {
+            return None;
+        }
+        Some((sx as f32 / n as f32, sy as f32 / n as f32))
+    }
+
+    // The live part of the board as text, one line per row with `#` for live
+    // cells and `.` for dead ones, trimmed to the bounding box.  An empty
+    // board gives an empty string.
+    pub fn to_ascii(&self) -> String {
+        let (x0, y0, x1, y1) = match self.bounding_box() {
+            Some(bbox) => bbox,
+            None => return String::new()
+        };
+        let mut out = String::with_capacity((x1 - x0 + 2) * (y1 - y0 + 1));
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                out.push(if self.cell(x, y).alive { '#' } else { '.' });
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    // Whether any live cell sits on an edge of the map that doesn't wrap,
+    // where the missing neighbours make the results untrustworthy.  Only the
+    // outermost ring is scanned.
+    pub fn touches_border(&self) -> bool {
+        let (w, h) = (self.width, self.height);
+        let (rows, columns) = match self.boundary {
+            BoundaryMode::Wrap => (false, false),
+            BoundaryMode::CylinderX => (true, false),
+            BoundaryMode::Dead | BoundaryMode::Mirror => (true, true)
+        };
+        let alive = |x, y| self.cell(x, y).alive;
+        (rows && (0..w).any(|x| alive(x, 0) || alive(x, h - 1)))
+            || (columns && (0..h).any(|y| alive(0, y) || alive(w - 1, y)))
+    }
+
+    // Map a neighbour offset from (x, y) to map coordinates, or None if the
+    // neighbour lies outside a non-wrapping map or in the margin.
+    fn neighbour(&self, x: usize, y: usize, dx: i32, dy: i32) -> Option<(usize, usize)> {
+        neighbour_of(self.boundary, self.width, self.height, x as i32 + dx, y as i32 + dy)
+            .filter(|&(i, j)| !self.in_margin(i, j))
+    }
+
+    fn in_margin(&self, x: usize, y: usize) -> bool {
+        let m = self.margin;
+        m > 0 && (x < m || y < m || x + m >= self.width || y + m >= self.height)
+    }
+
+    // Past a Dead edge, neighbours off the map count as dead, so a pattern
+    // there runs into a wall rather than open space.  A margin keeps the same
+    // wall `margin` cells in, but its cells are still on the map: they're
+    // drawn, can be edited and keep their trails, yet never count as live
+    // neighbours, whatever their state.
+    pub fn live_neighbours(&self, x: usize, y: usize) -> i32 {
+        let (orthogonal, diagonal) = self.live_neighbours_split(x, y);
+        orthogonal + diagonal
+    }
+
+    // The live neighbours of (x, y) in its row or column, and the rest
+    pub fn live_neighbours_split(&self, x: usize, y: usize) -> (i32, i32) {
+        self.count_neighbours(x, y, |cell| cell.alive)
+    }
+
+    // Number of neighbours of (x, y) for which `pred` holds, as orthogonal
+    // and diagonal counts.  Every neighbour on the hex grid shares an edge
+    // with the cell, so they all count as orthogonal.
+    fn count_neighbours<F: Fn(&Cell) -> bool>(&self, x: usize, y: usize, pred: F) -> (i32, i32) {
+        let mut count = (0, 0);
+        if self.neighbourhood == Neighbourhood::Hex {
+            let offsets = if y & 1 == 0 { &HEX_EVEN } else { &HEX_ODD };
+            for &(dx, dy) in offsets {
+                if let Some((i, j)) = self.neighbour(x, y, dx, dy) {
+                    if pred(self.cell(i, j)) { count.0 += 1 };
+                }
+            }
+            return count;
+        }
+        let r = self.radius as i32;
+        for dx in -r..r + 1 {
+            for dy in -r..r + 1 {
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+                if self.neighbourhood == Neighbourhood::VonNeumann && dx.abs() + dy.abs() > r {
+                    continue;
+                }
+                if let Some((i, j)) = self.neighbour(x, y, dx, dy) {
+                    if !pred(self.cell(i, j)) {
+                        continue;
+                    }
+                    if dx == 0 || dy == 0 { count.0 += 1 } else { count.1 += 1 };
+                }
+            }
+        }
+        count
+    }
+
+    // How many cells count as neighbours of each cell
+    fn neighbourhood_size(&self) -> i32 {
+        let r = self.radius as i32;
+        match self.neighbourhood {
+            Neighbourhood::Moore => (2 * r + 1) * (2 * r + 1) - 1,
+            Neighbourhood::VonNeumann => 2 * r * (r + 1),
+            Neighbourhood::Hex => HEX_EVEN.len() as i32
+        }
+    }
+
+    // Under a two-state B0 rule the empty plane around the board comes to
+    // life all at once, which a finite grid can't show.  Instead cells are
+    // stored relative to that background, so a live cell is one that differs
+    // from it and an empty board stays empty.
+    fn flips_background(&self) -> bool {
+        self.rule.states == 2 && self.rule.birth.first() == Some(&0)
+    }
+
+    // Whether the background is currently alive.  It's born in the first
+    // generation, then stays alive under rules that let a cell survive with
+    // every neighbour alive, and dies and is reborn every generation under
+    // the rest.
+    pub fn background(&self) -> bool {
+        self.background_at(self.generation)
+    }
+
+    fn background_at(&self, generation: u64) -> bool {
+        if !self.flips_background() || generation == 0 {
+            return false;
+        }
+        let size = self.neighbourhood_size();
+        self.rule.survival.iter().any(|&c| c as i32 == size) || generation & 1 == 1
+    }
+
+    // The state of the cell at (x, y) in the next generation
+    fn next_cell(&self, x: usize, y: usize) -> Cell {
+        if self.in_margin(x, y) {
+            let cell = self.cell(x, y);
+            return Cell { linger: cell.linger.saturating_sub(1), color: cell.color, ..Cell::default() };
+        }
+        // Large radii can count past what a rule can ask for
+        let (orthogonal, diagonal) = self.live_neighbours_split(x, y);
+        let count = orthogonal + diagonal;
+        let n = count.min(u8::MAX as i32) as u8;
+        let cell = self.cell(x, y);
+        let (alive, dying, age) = match self.kind {
+            // Work with the real states, then store the result relative to
+            // the next generation's background
+            SimKind::Life | SimKind::Immigration if self.flips_background() => {
+                let (alive, count) = if self.background() {
+                    (!cell.alive, self.neighbourhood_size() - count)
+                } else {
+                    (cell.alive, count)
+                };
+                let counts = if alive { &self.rule.survival } else { &self.rule.birth };
+                let next = counts.iter().any(|&c| c as i32 == count);
+                (next != self.background_at(self.generation.wrapping_add(1)), false, 0)
+            },
+            // Live cells consult the survival counts, dead cells the birth
+            // counts.  Under a Generations rule, failing to survive starts a
+            // cell dying, and it takes `states - 2` ticks to die completely.
+            SimKind::Life | SimKind::Immigration => if cell.alive {
+                if self.rule.survives(orthogonal, diagonal) && self.lucky(x, y, self.rule.survival_prob) {
+                    (true, false, 0)
+                }
+                else if self.rule.states > 2 { (false, true, 1) }
+                else { (false, false, 0) }
+            } else if cell.dying {
+                if cell.age + 2 < self.rule.states { (false, true, cell.age + 1) }
+                else { (false, false, 0) }
+            } else {
+                (self.rule.born(orthogonal, diagonal) && self.lucky(x, y, self.rule.birth_prob), false, 0)
+            },
+            SimKind::BriansBrain => if cell.alive {
+                (false, true, 0)
+            } else {
+                (!cell.dying && n == 2, false, 0)
+            }
+        };
+        // Live cells brighten up to the trail length, dead cells fade to 0
+        let linger = if alive {
+            cell.linger.saturating_add(1).min(self.trail_length)
+        } else {
+            cell.linger.saturating_sub(1)
+        };
+        // Newborns side with the majority of their parents, ties going to 0
+        let color = if self.kind == SimKind::Immigration && alive && !cell.alive {
+            let (o, d) = self.count_neighbours(x, y, |c| c.alive && c.color == 1);
+            if 2 * (o + d) > n as i32 { 1 } else { 0 }
+        } else {
+            cell.color
+        };
+        let alive_for = if alive && cell.alive { cell.alive_for.saturating_add(1) } else { 0 };
+        Cell { alive, linger, dying, age, alive_for, color }
+    }
+
+    // Kill every cell and wipe the trails
+    pub fn clear(&mut self) {
+        for cell in &mut self.front {
+            *cell = Cell::default();
+        }
+        self.generation = 0;
+        self.prev_checksum = None;
+        self.period = None;
+        self.back_is_previous = false;
+        self.past.clear();
+    }
+
+    // Bring every dead cell to life and kill every live one.  Trails are
+    // reset to match, fully lit under the new live cells and gone elsewhere,
+    // and dying cells count as dead.
+    pub fn invert(&mut self) {
+        let lit = self.trail_length;
+        for cell in &mut self.front {
+            let alive = !cell.alive;
+            *cell = Cell {
+                alive, linger: if alive { lit } else { 0 }, dying: false, age: 0, alive_for: 0, color: cell.color
+            };
+        }
+        self.prev_checksum = None;
+        self.period = None;
+        self.back_is_previous = false;
+    }
+
+    // Remember which cells are alive so a following edit can be undone.  Only
+    // the last UNDO_DEPTH snapshots are kept.
+    pub fn snapshot(&mut self) {
+        let bits = self.pack_alive();
+        if self.history.len() == UNDO_DEPTH {
+            self.history.pop_front();
+        }
+        self.history.push_back(bits);
+    }
+
+    // Go back to the most recent snapshot, returning false if there isn't
+    // one.  Trails are left as they are.
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop_back() {
+            Some(bits) => {
+                self.unpack_alive(&bits);
+                true
+            },
+            None => false
+        }
+    }
+
+    // Return to the generation before this one, as saved by tick(), or do
+    // nothing and return false once the saved generations run out
+    pub fn step_back(&mut self) -> bool {
+        match self.past.pop_back() {
+            Some((generation, bits)) => {
+                self.unpack_alive(&bits);
+                self.generation = generation;
+                true
+            },
+            None => false
+        }
+    }
+
+    // Which cells are alive, one bit per cell
+    fn pack_alive(&self) -> Vec<u64> {
+        let mut bits = vec![0u64; self.front.len().div_ceil(64)];
+        for (i, cell) in self.front.iter().enumerate() {
+            if cell.alive { bits[i / 64] |= 1 << (i % 64) };
+        }
+        bits
+    }
+
+    fn unpack_alive(&mut self, bits: &[u64]) {
+        for (i, cell) in self.front.iter_mut().enumerate() {
+            cell.alive = bits[i / 64] & (1 << (i % 64)) != 0;
+            cell.dying = false;
+            cell.age = 0;
+            cell.alive_for = 0;
+        }
+        self.prev_checksum = None;
+        self.period = None;
+        self.back_is_previous = false;
+    }
+
+    pub fn init_noise(&mut self) {
+        self.init_noise_seeded(time_seed());
+    }
+
+    // The same seed always produces the same starting board.  Any existing
+    // state, including fade trails, is cleared first.
+    pub fn init_noise_seeded(&mut self, seed: u32) {
+        let noise2d = Perlin::new(seed);
+        // A second field splits the board into patches of each colour
+        let colors = Perlin::new(seed ^ 0x5bd1_e995);
+        info!("seeding {}x{} board from noise with seed {}", self.width, self.height, seed);
+        self.clear();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let nx = (x as f32 * self.noise_scale_x) / self.width as f32;
+                let ny = (y as f32 * self.noise_scale_y) / self.height as f32;
+                let n = noise2d.fbm(nx, ny, self.noise_octaves, self.noise_persistence);
+                if n >= self.noise_threshold { self.set_alive(x, y, true) };
+                let i = self.idx(x, y);
+                self.front[i].color = (colors.get(nx, ny) >= 0.0) as u8;
+            }
+        }
+    }
+
+    // Uniform random fill: each cell is alive with probability `density`,
+    // which is clamped to [0, 1].  Like init_noise_seeded this clears first.
+    pub fn init_random(&mut self, density: f32, seed: u32) {
+        let (w, h) = (self.width, self.height);
+        info!("seeding {}x{} board at density {} with seed {}", w, h, density, seed);
+        self.clear();
+        self.soup(0, 0, w, h, density, seed);
+    }
+
+    // Replace the w x h block at (x0, y0) with a random soup, leaving the rest
+    // of the map alone.  The part of the block off the map is dropped.
+    pub fn soup(&mut self, x0: usize, y0: usize, w: usize, h: usize, density: f32, seed: u32) {
+        let density = density.clamp(0.0, 1.0) as f64;
+        let mut rng = Rng::new(seed);
+        for y in y0..(y0 + h).min(self.height) {
+            for x in x0..(x0 + w).min(self.width) {
+                let roll = rng.next_u32() as f64 / (u32::MAX as f64 + 1.0);
+                self.set_alive(x, y, roll < density);
+                let i = self.idx(x, y);
+                self.front[i].color = (rng.next_u32() & 1) as u8;
+            }
+        }
+    }
+
+    // Like soup, but filling a disc of the given radius around (cx, cy).
+    // Whatever of it falls off the map is dropped.
+    pub fn stamp_noise_blob(&mut self, cx: i32, cy: i32, radius: usize, density: f32, seed: u32) {
+        let density = density.clamp(0.0, 1.0) as f64;
+        let mut rng = Rng::new(seed);
+        let r = radius as i32;
+        for dy in -r..r + 1 {
+            for dx in -r..r + 1 {
+                if dx * dx + dy * dy > r * r {
+                    continue;
+                }
+                let roll = rng.next_u32() as f64 / (u32::MAX as f64 + 1.0);
+                self.paint(cx + dx, cy + dy, roll < density);
+            }
+        }
+    }
+
+    // A random fill like init_random, but with the given symmetry about the
+    // middle of the map.  A rotated soup only fits in a square, so it fills
+    // the largest square in the middle and leaves the rest empty.
+    pub fn init_symmetric_soup(&mut self, kind: SymmetryKind, density: f32, seed: u32) {
+        self.init_random(density, seed);
+        let (w, h) = (self.width, self.height);
+        let n = w.min(h);
+        let (left, top) = ((w - n) / 2, (h - n) / 2);
+        for y in 0..h {
+            for x in 0..w {
+                // The cell whose state this one copies
+                let source = match kind {
+                    SymmetryKind::None => Some((x, y)),
+                    SymmetryKind::MirrorX => Some((x.min(w - 1 - x), y)),
+                    SymmetryKind::MirrorXY => Some((x.min(w - 1 - x), y.min(h - 1 - y))),
+                    SymmetryKind::Rotate4 if x < left || y < top || x >= left + n || y >= top + n => None,
+                    SymmetryKind::Rotate4 => {
+                        let (a, b) = (x - left, y - top);
+                        let turns = [(a, b), (n - 1 - b, a), (n - 1 - a, n - 1 - b), (b, n - 1 - a)];
+                        turns.iter().min().map(|&(a, b)| (a + left, b + top))
+                    }
+                };
+                let i = self.idx(x, y);
+                self.front[i] = match source {
+                    Some((sx, sy)) => *self.cell(sx, sy),
+                    None => Cell::default()
+                };
+            }
+        }
+    }
+
+    // Clicks outside the map are silently ignored
+    pub fn toggle(&mut self, x: i32, y: i32) {
+        if x < 0 || y < 0 { return };
+        let (x, y) = (x as usize, y as usize);
+        if x >= self.width || y >= self.height { return };
+        let alive = self.cell(x, y).alive;
+        self.set_alive(x, y, !alive);
+    }
+
+    // Like set_alive, but ignores positions outside the map
+    pub fn paint(&mut self, x: i32, y: i32, alive: bool) {
+        if x < 0 || y < 0 { return };
+        let (x, y) = (x as usize, y as usize);
+        if x >= self.width || y >= self.height { return };
+        self.set_alive(x, y, alive);
+    }
+
+    // Switch to `rule`, keeping the current odds, which aren't part of the
+    // notation.  Dying cells further along than the new rule's states reach
+    // finish dying at once.
+    pub fn set_rule(&mut self, mut rule: Ruleset) {
+        rule.birth_prob = self.rule.birth_prob;
+        rule.survival_prob = self.rule.survival_prob;
+        if self.kind != SimKind::BriansBrain {
+            for cell in self.front.iter_mut().filter(|c| c.dying && c.age >= rule.states - 1) {
+                cell.dying = false;
+                cell.age = 0;
+            }
+        }
+        self.rule = rule;
+    }
+
+    // Seed the random numbers used by stochastic rules.  Together with the
+    // generation this fixes every roll, so the same seed replays the same run.
+    pub fn set_rng_seed(&mut self, seed: u32) {
+        self.rng_seed = seed;
+    }
+
+    // Whether the cell at (x, y) beats odds of `chance` this generation.  A
+    // cell is only ever up for birth or survival, never both, so one roll
+    // each generation covers it.
+    fn lucky(&self, x: usize, y: usize, chance: f32) -> bool {
+        chance >= 1.0 || roll(self.rng_seed, self.generation, self.idx(x, y)) < chance as f64
+    }
+
+    // Cells are worked out in row-major order, the order idx() numbers them
+    // in.  Nothing a cell does can depend on that order: each one's random
+    // roll comes from its own index, so the parallel compute() agrees.
+    fn compute_serial(&self, next: &mut [Cell]) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                next[self.idx(x, y)] = self.next_cell(x, y);
+            }
+        }
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    fn compute(&self, next: &mut [Cell]) {
+        self.compute_serial(next);
+    }
+
+    // Each cell of the next generation only reads the current one, so they
+    // can all be worked out in parallel.
+    #[cfg(feature = "rayon")]
+    fn compute(&self, next: &mut [Cell]) {
+        if !self.parallel {
+            return self.compute_serial(next);
+        }
+        let w = self.width;
+        next.par_iter_mut().enumerate()
+            .for_each(|(i, cell)| *cell = self.next_cell(i % w, i / w));
+    }
+
+    // Advance one generation.  Returns false if no cell changed state, i.e.
+    // the board has settled into a still life.
+    pub fn tick(&mut self) -> bool {
+        // Work out the next generation into the back buffer, then swap it to
+        // the front.  Keeping the two apart means cells updated earlier in the
+        // array can't affect cells further along.
+        let before = self.checksum();
+        let period = self.period;
+        if self.rewind_depth > 0 {
+            while self.past.len() >= self.rewind_depth {
+                self.past.pop_front();
+            }
+            let bits = self.pack_alive();
+            self.past.push_back((self.generation, bits));
+        }
+        let mut next = mem::take(&mut self.back);
+        self.compute(&mut next);
+        self.changed_count = self.front.iter().zip(next.iter()).filter(|&(a, b)| a.alive != b.alive).count();
+        let changed = self.changed_count > 0 || self.front.iter().zip(next.iter())
+            .any(|(a, b)| a.dying != b.dying || a.age != b.age);
+        self.back = mem::replace(&mut self.front, next);
+        self.back_is_previous = true;
+        self.generation = self.generation.wrapping_add(1);
+
+        self.period = if !changed {
+            Some(1)
+        } else if Some(self.checksum()) == self.prev_checksum {
+            Some(2)
+        } else {
+            None
+        };
+        self.prev_checksum = Some(before);
+        if self.period != period {
+            match self.period {
+                Some(1) => info!("stabilized at generation {}", self.generation),
+                Some(p) => info!("oscillating with period {} at generation {}", p, self.generation),
+                None => debug!("changing again at generation {}", self.generation)
+            }
+        }
+        changed
+    }
+
+    // FNV-1a hash of which cells are alive, for cheaply comparing generations
+    pub fn checksum(&self) -> u64 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for (i, cell) in self.front.iter().enumerate() {
+            if cell.alive || cell.dying {
+                let state = (i as u64) << 9 | (cell.age as u64) << 1 | cell.dying as u64;
+                hash = (hash ^ state).wrapping_mul(0x0000_0100_0000_01b3);
+            }
+        }
+        hash
+    }
+
+    // Some(1) if the last tick repeated the generation before it, Some(2) if
+    // it repeated the one before that, e.g. a field of blinkers
+    pub fn period(&self) -> Option<u32> {
+        self.period
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rules::DEFAULT_RULE;
+
+    fn conway() -> Map {
+        Map::new(MAP_WIDTH, MAP_HEIGHT, Ruleset::parse(DEFAULT_RULE).unwrap())
+    }
+
+    // A small Life board holding just the given cells
+    fn small(cells: &[(usize, usize)]) -> Map {
+        let mut map = Map::new(8, 8, Ruleset::parse(DEFAULT_RULE).unwrap());
+        for &(x, y) in cells {
+            map.set_alive(x, y, true);
+        }
+        map
+    }
+
+    fn live(map: &Map) -> Vec<(usize, usize)> {
+        map.live_iter().collect()
+    }
+
+    #[test]
+    fn live_iter_is_row_major() {
+        let map = small(&[(5, 1), (2, 6), (0, 1), (7, 7)]);
+        assert_eq!(live(&map), [(0, 1), (5, 1), (2, 6), (7, 7)]);
+    }
+
+    #[test]
+    fn block_is_a_still_life() {
+        let block = [(3, 3), (4, 3), (3, 4), (4, 4)];
+        let mut map = small(&block);
+        for _ in 0..3 {
+            map.tick();
+            assert_eq!(live(&map), block);
+        }
+    }
+
+    #[test]
+    fn blinker_has_period_two() {
+        let horizontal = [(2, 3), (3, 3), (4, 3)];
+        let vertical = [(3, 2), (3, 3), (3, 4)];
+        let mut map = small(&horizontal);
+        map.tick();
+        assert_eq!(live(&map), vertical);
+        map.tick();
+        assert_eq!(live(&map), horizontal);
+        assert_eq!(map.period(), Some(2));
+    }
+
+    #[test]
+    fn glider_translates_after_four_ticks() {
+        let mut map = small(&[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        for _ in 0..4 {
+            map.tick();
+        }
+        assert_eq!(live(&map), [(2, 1), (3, 2), (1, 3), (2, 3), (3, 3)]);
+    }
+
+    #[test]
+    fn isolated_cell_dies() {
+        let mut map = small(&[(4, 4)]);
+        assert!(map.tick());
+        assert!(live(&map).is_empty());
+    }
+
+    #[test]
+    fn blinker_on_last_column_oscillates() {
+        let mut map = conway();
+        let (w, y) = (map.width, map.height / 2);
+        // Horizontal blinker whose right end sits in the last column
+        for x in (w - 3)..w {
+            map.toggle(x as i32, y as i32);
+        }
+        map.tick();
+        assert!(!map.cell(w - 1, y).alive);
+        assert!(map.cell(w - 2, y - 1).alive && map.cell(w - 2, y).alive && map.cell(w - 2, y + 1).alive);
+        assert_eq!(map.live_cells(), 3);
+        map.tick();
+        assert!(map.cell(w - 3, y).alive && map.cell(w - 2, y).alive && map.cell(w - 1, y).alive);
+        assert_eq!(map.live_cells(), 3);
+    }
+
+    #[test]
+    fn blinker_on_last_row_oscillates() {
+        let mut map = conway();
+        let (x, h) = (map.width / 2, map.height);
+        for y in (h - 3)..h {
+            map.toggle(x as i32, y as i32);
+        }
+        map.tick();
+        assert!(!map.cell(x, h - 1).alive);
+        assert!(map.cell(x - 1, h - 2).alive && map.cell(x, h - 2).alive && map.cell(x + 1, h - 2).alive);
+        map.tick();
+        assert!(map.cell(x, h - 3).alive && map.cell(x, h - 2).alive && map.cell(x, h - 1).alive);
+    }
+
+    #[test]
+    fn tick_reports_still_lifes() {
+        let mut map = conway();
+        // A block never changes
+        for &(x, y) in &[(10, 10), (11, 10), (10, 11), (11, 11)] {
+            map.set_alive(x, y, true);
+        }
+        assert!(!map.tick());
+        // ...but a blinker next to it does, even though it repeats
+        for x in 20..23 {
+            map.set_alive(x, 10, true);
+        }
+        assert!(map.tick());
+        assert!(map.tick());
+    }
+
+    #[test]
+    fn detects_period_two_oscillators() {
+        let mut map = conway();
+        for x in 20..23 {
+            map.set_alive(x, 10, true);
+        }
+        map.tick();
+        assert_eq!(map.period(), None);
+        map.tick();
+        assert_eq!(map.period(), Some(2));
+        map.tick();
+        assert_eq!(map.period(), Some(2));
+    }
+
+    #[test]
+    fn glider_moves_diagonally() {
+        let mut map = conway();
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        for &(x, y) in &glider {
+            map.set_alive(10 + x, 10 + y, true);
+        }
+        for _ in 0..4 {
+            map.tick();
+        }
+        assert_eq!(map.live_cells(), 5);
+        for &(x, y) in &glider {
+            assert!(map.cell(11 + x, 11 + y).alive);
+        }
+    }
+
+    #[test]
+    fn brians_brain_cycles_through_three_states() {
+        let mut map = conway();
+        map.kind = SimKind::BriansBrain;
+        // Two on cells give birth to the cells either side of them
+        map.set_alive(10, 10, true);
+        map.set_alive(11, 10, true);
+        map.tick();
+        assert!(map.cell(10, 10).dying && map.cell(11, 10).dying);
+        assert!(map.cell(10, 9).alive && map.cell(11, 9).alive);
+        assert!(map.cell(10, 11).alive && map.cell(11, 11).alive);
+        map.tick();
+        assert!(!map.cell(10, 10).alive && !map.cell(10, 10).dying);
+        assert!(map.cell(10, 9).dying && map.cell(10, 11).dying);
+    }
+
+    #[test]
+    fn glider_wraps_around_cylinder() {
+        let mut map = conway();
+        map.boundary = BoundaryMode::CylinderX;
+        let w = map.width;
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        for &(x, y) in &glider {
+            map.set_alive(w - 5 + x, 20 + y, true);
+        }
+        // Ten cells right and down, which takes it across the right edge
+        for _ in 0..40 {
+            map.tick();
+        }
+        assert_eq!(map.live_cells(), 5);
+        for &(x, y) in &glider {
+            assert!(map.cell((w + 5 + x) % w, 30 + y).alive);
+        }
+    }
+
+    #[test]
+    fn mirror_reflects_about_the_edge() {
+        let mut map = conway();
+        map.boundary = BoundaryMode::Mirror;
+        // The corner (0, 0) sees (1, 1) directly, and again through the
+        // reflections of (-1, -1), (1, -1) and (-1, 1)
+        map.set_alive(1, 1, true);
+        assert_eq!(map.live_neighbours(0, 0), 4);
+        // Likewise at the opposite corner, where width reflects to width - 2
+        let (w, h) = (map.width, map.height);
+        map.set_alive(w - 2, h - 2, true);
+        assert_eq!(map.live_neighbours(w - 1, h - 1), 4);
+        // Under a dead boundary only the real neighbour counts
+        map.boundary = BoundaryMode::Dead;
+        assert_eq!(map.live_neighbours(0, 0), 1);
+    }
+
+    #[test]
+    fn undo_restores_snapshots() {
+        let mut map = conway();
+        map.set_alive(3, 3, true);
+        map.snapshot();
+        map.set_alive(4, 4, true);
+        map.snapshot();
+        map.clear();
+        assert!(map.undo());
+        assert_eq!(map.live_cells(), 2);
+        assert!(map.undo());
+        assert_eq!(map.live_cells(), 1);
+        assert!(map.cell(3, 3).alive);
+        assert!(!map.undo());
+    }
+
+    #[test]
+    fn undo_history_is_bounded() {
+        let mut map = conway();
+        for _ in 0..UNDO_DEPTH + 5 {
+            map.snapshot();
+        }
+        let mut undone = 0;
+        while map.undo() {
+            undone += 1;
+        }
+        assert_eq!(undone, UNDO_DEPTH);
+    }
+
+    #[test]
+    fn centres_the_view_within_the_map() {
+        let mut map = conway();
+        map.center_view_on(150, 40, 80, 40);
+        assert_eq!((map.o_x, map.o_y), (110, 20));
+        map.center_view_on(5, 75, 80, 40);
+        assert_eq!((map.o_x, map.o_y), (0, MAP_HEIGHT as i32 - 40));
+    }
+
+    #[test]
+    fn prints_the_live_region_as_ascii() {
+        assert_eq!(small(&[]).to_ascii(), "");
+        let map = small(&[(3, 2), (4, 3), (2, 4), (3, 4), (4, 4)]);
+        assert_eq!(map.to_ascii(), ".#.\n..#\n###\n");
+    }
+
+    #[test]
+    fn notices_cells_on_the_border() {
+        let mut map = small(&[(3, 3)]);
+        assert!(!map.touches_border());
+        map.set_alive(7, 4, true);
+        assert!(map.touches_border());
+        // Left and right edges wrap on a cylinder, so only the top and
+        // bottom count
+        map.boundary = BoundaryMode::CylinderX;
+        assert!(!map.touches_border());
+        map.set_alive(2, 0, true);
+        assert!(map.touches_border());
+        map.boundary = BoundaryMode::Wrap;
+        assert!(!map.touches_border());
+    }
+
+    #[test]
+    fn b0_rules_dont_fill_the_board() {
+        let mut map = Map::new(20, 20, Ruleset::parse("B0/S2").unwrap());
+        assert!(!map.background());
+        for generation in 1..5 {
+            map.tick();
+            assert_eq!(map.live_cells(), 0);
+            // No S8, so the background strobes
+            assert_eq!(map.background(), generation % 2 == 1);
+        }
+    }
+
+    #[test]
+    fn antilife_mirrors_life_against_a_live_background() {
+        // AntiLife is Life with alive and dead swapped, so against its live
+        // background a glider should glide just as it does in Life.  Start
+        // after the first generation, once that background has been born.
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        let mut life = small(&glider);
+        let mut anti = small(&glider);
+        anti.rule = Ruleset::parse("antilife").unwrap();
+        anti.generation = 1;
+        for _ in 0..4 {
+            life.tick();
+            anti.tick();
+            assert!(anti.background());
+            assert_eq!(live(&anti), live(&life));
+        }
+    }
+
+    #[test]
+    fn noise_threshold_thins_the_board() {
+        let mut map = conway();
+        map.init_noise_seeded(3);
+        let normal = map.live_cells();
+        map.noise_threshold = 0.3;
+        map.init_noise_seeded(3);
+        let thinned = map.live_cells();
+        assert!(thinned > 0 && thinned < normal);
+        // Nothing clears a threshold past the top of the noise's range
+        map.noise_threshold = 2.0;
+        map.init_noise_seeded(3);
+        assert_eq!(map.live_cells(), 0);
+    }
+
+    #[test]
+    fn noise_seeds_the_last_row_and_column() {
+        let mut map = Map::new(12, 8, Ruleset::parse(DEFAULT_RULE).unwrap());
+        let (w, h) = (map.width, map.height);
+        // Below the bottom of the noise's range every cell clears it,
+        // including those along the far edges
+        map.noise_threshold = -2.0;
+        map.init_noise_seeded(3);
+        assert_eq!(map.live_cells() as usize, w * h);
+        assert!((0..w).all(|x| map.cell(x, h - 1).alive) && (0..h).all(|y| map.cell(w - 1, y).alive));
+        // And at the usual threshold they come out like any other cells
+        map.noise_threshold = 0.0;
+        let (mut row, mut column) = (false, false);
+        for seed in 0..20 {
+            map.init_noise_seeded(seed);
+            row |= (0..w).any(|x| map.cell(x, h - 1).alive);
+            column |= (0..h).any(|y| map.cell(w - 1, y).alive);
+        }
+        assert!(row && column);
+    }
+
+    #[test]
+    fn octaves_add_detail_to_the_noise() {
+        let mut smooth = conway();
+        smooth.init_noise_seeded(3);
+        let mut detailed = conway();
+        detailed.noise_octaves = 4;
+        detailed.init_noise_seeded(3);
+        // Finer layers break the blobs up, so more cells differ from their
+        // right hand neighbour
+        let edges = |map: &Map| (0..map.height)
+            .flat_map(|y| (1..map.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| map.cell(x, y).alive != map.cell(x - 1, y).alive)
+            .count();
+        assert!(edges(&detailed) > edges(&smooth));
+    }
+
+    #[test]
+    fn follow_eases_towards_the_live_cells() {
+        let mut map = Map::new(100, 100, Ruleset::parse(DEFAULT_RULE).unwrap());
+        map.set_alive(80, 60, true);
+        map.set_alive(82, 60, true);
+        assert_eq!(map.center_of_mass(), Some((81.0, 60.0)));
+        map.follow(20, 10, 0.5);
+        assert_eq!((map.o_x, map.o_y), (35, 27));
+        for _ in 0..20 {
+            map.follow(20, 10, 0.5);
+        }
+        assert_eq!((map.o_x, map.o_y), (71, 55));
+        // Never past the edge of the map
+        map.set_alive(82, 60, false);
+        map.set_alive(80, 60, false);
+        map.set_alive(99, 99, true);
+        for _ in 0..20 {
+            map.follow(20, 10, 0.5);
+        }
+        assert_eq!((map.o_x, map.o_y), (80, 90));
+    }
+
+    #[test]
+    fn alive_for_counts_survived_generations() {
+        // A blinker's centre never dies, its ends are reborn every tick
+        let mut map = small(&[(2, 3), (3, 3), (4, 3)]);
+        for _ in 0..4 {
+            map.tick();
+        }
+        assert_eq!(map.cell(3, 3).alive_for, 4);
+        assert_eq!(map.cell(2, 3).alive_for, 0);
+        map.set_alive(3, 3, false);
+        assert_eq!(map.cell(3, 3).alive_for, 0);
+    }
+
+    #[test]
+    fn symmetric_soups_are_symmetric() {
+        let mut map = Map::new(21, 14, Ruleset::parse(DEFAULT_RULE).unwrap());
+        map.init_symmetric_soup(SymmetryKind::MirrorXY, 0.5, 9);
+        assert!(map.live_cells() > 0);
+        for (x, y) in live(&map) {
+            assert!(map.cell(20 - x, y).alive && map.cell(x, 13 - y).alive);
+        }
+        map.init_symmetric_soup(SymmetryKind::Rotate4, 0.5, 9);
+        assert!(map.live_cells() > 0);
+        for (x, y) in live(&map) {
+            // Inside the 14 x 14 square at (3, 0), the same turned a quarter
+            assert!((3..17).contains(&x));
+            let (a, b) = (x - 3, y);
+            assert!(map.cell(13 - b + 3, a).alive);
+        }
+    }
+
+    #[test]
+    fn knows_which_cells_changed_in_the_last_tick() {
+        let mut map = small(&[(2, 3), (3, 3), (4, 3)]);
+        assert!(!map.changed(2, 3));
+        map.tick();
+        assert!(map.changed(2, 3) && map.changed(3, 2) && !map.changed(3, 3) && !map.changed(0, 0));
+        // Both ends of the blinker die and two cells are born
+        assert_eq!(map.changed_count(), 4);
+        // An edit leaves nothing to show
+        map.set_alive(0, 0, true);
+        assert!(!map.changed(2, 3));
+        assert_eq!(map.changed_count(), 0);
+    }
+
+    #[test]
+    fn stochastic_survival_replays_from_its_seed() {
+        let run = |seed: u32| {
+            let mut map = conway();
+            map.init_noise_seeded(4);
+            map.rule.survival_prob = 0.9;
+            map.set_rng_seed(seed);
+            for _ in 0..10 {
+                map.tick();
+            }
+            live(&map)
+        };
+        assert_eq!(run(1), run(1));
+        assert_ne!(run(1), run(2));
+        // Certain survival is plain Life
+        let mut certain = conway();
+        certain.init_noise_seeded(4);
+        let mut plain = certain.clone();
+        certain.set_rng_seed(3);
+        for _ in 0..10 {
+            certain.tick();
+            plain.tick();
+        }
+        assert_eq!(live(&certain), live(&plain));
+    }
+
+    #[test]
+    fn nothing_is_born_without_a_chance() {
+        let mut map = conway();
+        map.init_noise_seeded(4);
+        map.rule.birth_prob = 0.0;
+        map.rule.survival_prob = 0.5;
+        let mut alive: Vec<_> = live(&map);
+        for _ in 0..10 {
+            map.tick();
+            let now = live(&map);
+            assert!(now.iter().all(|cell| alive.contains(cell)));
+            alive = now;
+        }
+    }
+
+    #[test]
+    fn generations_wrap_instead_of_overflowing() {
+        for &rule in &[DEFAULT_RULE, "antilife", "B2/S/C4"] {
+            let mut map = Map::new(20, 20, Ruleset::parse(rule).unwrap());
+            map.init_random(0.4, 2);
+            map.generation = u64::MAX - 2;
+            for _ in 0..5 {
+                map.tick();
+            }
+            assert_eq!(map.generation, 2);
+            while map.step_back() {}
+            assert_eq!(map.generation, u64::MAX - 2);
+        }
+    }
+
+    #[test]
+    fn margins_behave_like_a_smaller_map() {
+        // A soup spreading into the margin ends up as it would against the
+        // dead edges of a map with the margin cut off
+        let mut inner = Map::new(14, 14, Ruleset::parse(DEFAULT_RULE).unwrap());
+        inner.init_random(0.4, 9);
+        let mut map = Map::new(20, 20, Ruleset::parse(DEFAULT_RULE).unwrap());
+        map.margin = 3;
+        for (x, y) in inner.live_iter() {
+            map.set_alive(x + 3, y + 3, true);
+        }
+        // Live cells painted into the margin don't count, and die
+        map.set_alive(2, 10, true);
+        map.set_alive(2, 11, true);
+        map.set_alive(2, 12, true);
+        for _ in 0..30 {
+            inner.tick();
+            map.tick();
+            let shifted: Vec<(usize, usize)> = inner.live_iter().map(|(x, y)| (x + 3, y + 3)).collect();
+            assert_eq!(live(&map), shifted);
+        }
+    }
+
+    #[test]
+    fn trails_last_the_trail_length() {
+        let block = [(1, 1), (2, 1), (1, 2), (2, 2)];
+        let mut map = small(&block);
+        map.trail_length = 3;
+        let mut trail = Vec::new();
+        for _ in 0..4 {
+            map.tick();
+            trail.push(map.cell(1, 1).linger);
+        }
+        // Then fading once the block is gone
+        for &(x, y) in &block {
+            map.set_alive(x, y, false);
+        }
+        for _ in 0..4 {
+            map.tick();
+            trail.push(map.cell(1, 1).linger);
+        }
+        assert_eq!(trail, [1, 2, 3, 3, 2, 1, 0, 0]);
+    }
+
+    #[test]
+    fn inverts_every_cell() {
+        let mut map = small(&[(0, 0), (7, 7), (3, 4)]);
+        map.invert();
+        assert_eq!(map.live_cells(), 64 - 3);
+        assert!(!map.cell(0, 0).alive && !map.cell(7, 7).alive);
+        assert!(map.cell(7, 0).alive && map.cell(0, 7).alive);
+        assert_eq!((map.cell(7, 0).linger, map.cell(3, 4).linger), (9, 0));
+        map.invert();
+        assert_eq!(live(&map), vec![(0, 0), (3, 4), (7, 7)]);
+    }
+
+    #[test]
+    fn random_fill_follows_density() {
+        let mut map = conway();
+        let total = (map.width * map.height) as i32;
+        map.init_random(0.0, 7);
+        assert_eq!(map.live_cells(), 0);
+        map.init_random(1.0, 7);
+        assert_eq!(map.live_cells(), total);
+        // Out of range densities are clamped
+        map.init_random(-0.5, 7);
+        assert_eq!(map.live_cells(), 0);
+        map.init_random(0.35, 7);
+        let live = map.live_cells();
+        assert!(live > total / 4 && live < total / 2);
+    }
+
+    #[test]
+    fn soup_stays_inside_its_block() {
+        let mut map = small(&[(0, 0), (7, 7)]);
+        map.soup(2, 2, 3, 3, 1.0, 7);
+        assert_eq!(map.live_cells(), 11);
+        assert!(map.cell(0, 0).alive && map.cell(7, 7).alive);
+        assert!(map.cell(4, 4).alive && !map.cell(5, 5).alive);
+        // The same seed gives the same soup, and blocks hanging off the edge
+        // are cut short
+        let (mut a, mut b) = (small(&[]), small(&[]));
+        a.soup(4, 4, 10, 10, 0.5, 3);
+        b.soup(4, 4, 10, 10, 0.5, 3);
+        assert_eq!(live(&a), live(&b));
+        assert!(live(&a).iter().all(|&(x, y)| x >= 4 && y >= 4));
+    }
+
+    #[test]
+    fn generations_cells_age_before_dying() {
+        let mut map = Map::new(MAP_WIDTH, MAP_HEIGHT, Ruleset::parse("B2/S345/C4").unwrap());
+        // A lone cell can't survive, so it spends two ticks dying
+        map.set_alive(10, 10, true);
+        map.tick();
+        let cell = *map.cell(10, 10);
+        assert!(!cell.alive && cell.dying && cell.age == 1);
+        map.tick();
+        let cell = *map.cell(10, 10);
+        assert!(!cell.alive && cell.dying && cell.age == 2);
+        map.tick();
+        assert!(!map.cell(10, 10).dying);
+        assert!(!map.tick());
+    }
+
+    #[test]
+    fn switching_rules_drops_cells_too_far_gone() {
+        let mut map = Map::new(MAP_WIDTH, MAP_HEIGHT, Ruleset::parse("B2/S345/C5").unwrap());
+        map.set_alive(10, 10, true);
+        map.set_alive(15, 15, true);
+        map.tick();
+        map.set_alive(15, 15, true);
+        map.tick();
+        assert_eq!((map.cell(10, 10).age, map.cell(15, 15).age), (2, 1));
+        // Three states leave room to be one tick into dying, but not two
+        map.set_rule(Ruleset::parse("B2/S345/C3").unwrap());
+        assert!(!map.cell(10, 10).dying && map.cell(15, 15).dying);
+        map.set_rule(Ruleset::parse(DEFAULT_RULE).unwrap());
+        assert!(!map.front.iter().any(|c| c.dying));
+    }
+
+    #[test]
+    fn dying_cells_are_not_reborn() {
+        let mut map = Map::new(MAP_WIDTH, MAP_HEIGHT, Ruleset::parse("B1/S/C3").unwrap());
+        map.set_alive(10, 10, true);
+        map.tick();
+        // Every neighbour of the old cell is born, but the cell itself only
+        // finishes dying
+        assert!(map.cell(10, 10).dying);
+        assert!(map.cell(11, 10).alive);
+        assert_eq!(map.live_cells(), 8);
+    }
+
+    #[test]
+    fn von_neumann_skips_diagonals() {
+        let mut map = conway();
+        map.neighbourhood = Neighbourhood::VonNeumann;
+        for y in 0..3 {
+            for x in 0..3 {
+                map.set_alive(x, y, true);
+            }
+        }
+        assert_eq!(map.live_neighbours(0, 0), 2);
+        assert_eq!(map.live_neighbours(1, 1), 4);
+        map.neighbourhood = Neighbourhood::Moore;
+        assert_eq!(map.live_neighbours(0, 0), 3);
+        assert_eq!(map.live_neighbours(1, 1), 8);
+    }
+
+    #[test]
+    fn noise_blobs_fill_a_disc() {
+        let mut map = conway();
+        map.stamp_noise_blob(10, 10, 2, 1.0, 1);
+        assert_eq!(map.live_cells(), 13);
+        assert_eq!(map.bounding_box(), Some((8, 8, 12, 12)));
+        // A blob over the corner only lands on the map's quarter of it
+        map.stamp_noise_blob(0, 0, 2, 1.0, 1);
+        assert_eq!(map.live_cells(), 13 + 6);
+        map.stamp_noise_blob(10, 10, 2, 0.0, 1);
+        assert_eq!(map.live_cells(), 6);
+        let blob = |seed: u32| {
+            let mut map = conway();
+            map.stamp_noise_blob(20, 20, 8, 0.5, seed);
+            live(&map)
+        };
+        assert_eq!(blob(4), blob(4));
+        assert_ne!(blob(4), blob(5));
+    }
+
+    #[test]
+    fn splits_orthogonal_from_diagonal_neighbours() {
+        let mut map = small(&[(1, 1), (2, 1), (3, 3)]);
+        assert_eq!(map.live_neighbours_split(2, 2), (1, 2));
+        // Two diagonal neighbours aren't enough when survival needs them
+        // orthogonal
+        map.rule = Ruleset::parse("B/S2:*").unwrap();
+        map.clear();
+        for &(x, y) in &[(0, 0), (1, 1), (2, 2), (5, 5), (6, 5), (5, 6)] {
+            map.set_alive(x, y, true);
+        }
+        map.tick();
+        assert_eq!(live(&map), [(5, 5)]);
+        // Life spelled out as splits runs just like B3/S23
+        let rule = Ruleset::parse("B3:0,2:1,1:2,0:3/S23").unwrap();
+        assert_eq!(Ruleset::parse(&rule.to_string()), Ok(rule.clone()));
+        assert_eq!(Ruleset::parse("B1:2/S23:*").unwrap().to_string(), "B1:2/S23:*");
+        let mut plain = conway();
+        plain.init_noise_seeded(5);
+        let mut split = plain.clone();
+        split.rule = rule;
+        for _ in 0..20 {
+            plain.tick();
+            split.tick();
+        }
+        assert_eq!(live(&split), live(&plain));
+        assert!(Ruleset::parse("B0:0/S").is_err());
+        assert!(Ruleset::parse("B5:1/S").is_err());
+    }
+
+    #[test]
+    fn counts_neighbours_within_radius() {
+        let mut map = conway();
+        map.radius = 2;
+        for y in 10..15 {
+            for x in 10..15 {
+                map.set_alive(x, y, true);
+            }
+        }
+        // Cells just outside the radius shouldn't be counted
+        map.set_alive(15, 12, true);
+        map.set_alive(12, 9, true);
+        assert_eq!(map.live_neighbours(12, 12), 24);
+        map.neighbourhood = Neighbourhood::VonNeumann;
+        assert_eq!(map.live_neighbours(12, 12), 12);
+    }
+
+    #[test]
+    fn boundaries_apply_at_larger_radii() {
+        let mut map = conway();
+        map.radius = 2;
+        let w = map.width;
+        map.set_alive(w - 2, 5, true);
+        map.set_alive(2, 5, true);
+        assert_eq!(map.live_neighbours(0, 5), 1);
+        map.boundary = BoundaryMode::Wrap;
+        assert_eq!(map.live_neighbours(0, 5), 2);
+        // (-2, 5) reflects onto (2, 5), which is also a neighbour directly
+        map.boundary = BoundaryMode::Mirror;
+        assert_eq!(map.live_neighbours(0, 5), 2);
+    }
+
+    #[test]
+    fn immigrants_take_the_majority_colour() {
+        let mut map = conway();
+        map.kind = SimKind::Immigration;
+        // Two parents of colour 1 and one of colour 0 give a colour 1 child
+        // at (11, 11)
+        map.set_alive(10, 10, true);
+        map.set_alive(12, 10, true);
+        map.set_alive(11, 12, true);
+        let i = map.idx(10, 10);
+        map.front[i].color = 1;
+        let i = map.idx(12, 10);
+        map.front[i].color = 1;
+        map.tick();
+        assert!(map.cell(11, 11).alive);
+        assert_eq!(map.cell(11, 11).color, 1);
+    }
+
+    #[test]
+    fn noise_seeds_both_colours() {
+        let mut map = conway();
+        map.init_noise_seeded(3);
+        let ones = map.front.iter().filter(|c| c.alive && c.color == 1).count() as i32;
+        assert!(ones > 0 && ones < map.live_cells());
+    }
+
+    #[test]
+    fn steps_back_through_past_generations() {
+        let mut map = small(&[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        let start = live(&map);
+        map.tick();
+        let first = live(&map);
+        map.tick();
+        assert!(map.step_back());
+        assert_eq!(live(&map), first);
+        assert_eq!(map.generation, 1);
+        assert!(map.step_back());
+        assert_eq!(live(&map), start);
+        assert_eq!(map.generation, 0);
+        // Nothing further back to go to
+        assert!(!map.step_back());
+        assert_eq!(live(&map), start);
+    }
+
+    #[test]
+    fn rewind_history_is_bounded() {
+        let mut map = small(&[(2, 3), (3, 3), (4, 3)]);
+        map.rewind_depth = 3;
+        for _ in 0..10 {
+            map.tick();
+        }
+        let mut steps = 0;
+        while map.step_back() {
+            steps += 1;
+        }
+        assert_eq!(steps, 3);
+        assert_eq!(map.generation, 7);
+    }
+
+    #[test]
+    fn hex_neighbours_depend_on_row_parity() {
+        let mut map = small(&[]);
+        map.neighbourhood = Neighbourhood::Hex;
+        for y in 0..8 {
+            for x in 0..8 {
+                map.set_alive(x, y, true);
+            }
+        }
+        // Fully surrounded cells in even and odd rows both see six
+        assert_eq!(map.live_neighbours(3, 2), 6);
+        assert_eq!(map.live_neighbours(3, 3), 6);
+        // An even row leans left: (3, 2) touches (2, 1) but not (4, 1)
+        map.set_alive(4, 1, false);
+        assert_eq!(map.live_neighbours(3, 2), 6);
+        map.set_alive(2, 1, false);
+        assert_eq!(map.live_neighbours(3, 2), 5);
+        // An odd row leans right: (3, 3) touches (4, 4) but not (2, 4)
+        map.set_alive(2, 4, false);
+        assert_eq!(map.live_neighbours(3, 3), 6);
+        map.set_alive(4, 4, false);
+        assert_eq!(map.live_neighbours(3, 3), 5);
+    }
+
+    #[test]
+    fn same_seed_gives_same_board() {
+        let mut a = conway();
+        let mut b = conway();
+        a.init_noise_seeded(42);
+        b.init_noise_seeded(42);
+        assert!(a.live_cells() > 0);
+        assert_eq!(a.live_cells(), b.live_cells());
+        assert!(a.front.iter().zip(b.front.iter()).all(|(p, q)| p.alive == q.alive));
+
+        let mut c = conway();
+        c.init_noise_seeded(43);
+        assert!(a.front.iter().zip(c.front.iter()).any(|(p, q)| p.alive != q.alive));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_tick_matches_serial() {
+        let mut serial = conway();
+        let mut parallel = conway();
+        serial.init_noise_seeded(7);
+        parallel.init_noise_seeded(7);
+        parallel.parallel = true;
+        for _ in 0..10 {
+            serial.tick();
+            parallel.tick();
+        }
+        assert!(serial.front.iter().zip(parallel.front.iter()).all(|(p, q)| p.alive == q.alive));
+    }
+
+    #[test]
+    fn toggle_outside_map_is_ignored() {
+        let mut map = conway();
+        let (w, h) = (map.width as i32, map.height as i32);
+        map.toggle(w, h);
+        map.toggle(w, 0);
+        map.toggle(0, h);
+        map.toggle(-1, -1);
+        map.paint(w, 0, true);
+        map.paint(-1, 0, true);
+        assert_eq!(map.live_cells(), 0);
+    }
+}
@@ -0,0 +1,84 @@
+use rng::Rng;
+
+// 2D Perlin noise, used to seed the board with clumpy blobs of life.  Values
+// fall roughly in -1.0..1.0.
+pub struct Perlin {
+    perm: [u8; 512]
+}
+
+impl Perlin {
+    pub fn new(seed: u32) -> Perlin {
+        let mut rng = Rng::new(seed);
+        let mut p = [0u8; 256];
+        for (i, v) in p.iter_mut().enumerate() {
+            *v = i as u8;
+        }
+        // Fisher-Yates shuffle of the permutation table
+        for i in (1..256).rev() {
+            let j = (rng.next_u32() as usize) % (i + 1);
+            p.swap(i, j);
+        }
+        let mut perm = [0u8; 512];
+        for i in 0..512 {
+            perm[i] = p[i & 255];
+        }
+        Perlin { perm }
+    }
+
+    pub fn get(&self, x: f32, y: f32) -> f32 {
+        let xf = x.floor();
+        let yf = y.floor();
+        let xi = (xf as i32 & 255) as usize;
+        let yi = (yf as i32 & 255) as usize;
+        let x = x - xf;
+        let y = y - yf;
+        let u = fade(x);
+        let v = fade(y);
+
+        let p = &self.perm;
+        let aa = p[p[xi] as usize + yi];
+        let ab = p[p[xi] as usize + yi + 1];
+        let ba = p[p[xi + 1] as usize + yi];
+        let bb = p[p[xi + 1] as usize + yi + 1];
+
+        lerp(v,
+             lerp(u, grad(aa, x, y), grad(ba, x - 1.0, y)),
+             lerp(u, grad(ab, x, y - 1.0), grad(bb, x - 1.0, y - 1.0)))
+    }
+
+    // Fractal noise: `octaves` layers of noise, each at twice the frequency
+    // of the last and `persistence` times its weight, scaled back into the
+    // range of a single layer.  One octave is plain noise.
+    pub fn fbm(&self, x: f32, y: f32, octaves: u32, persistence: f32) -> f32 {
+        let (mut sum, mut total, mut weight, mut freq) = (0.0, 0.0, 1.0, 1.0);
+        for _ in 0..octaves.max(1) {
+            sum += weight * self.get(x * freq, y * freq);
+            total += weight;
+            weight *= persistence;
+            freq *= 2.0;
+        }
+        sum / total
+    }
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+
+// Pick one of eight gradient directions from the hash
+fn grad(hash: u8, x: f32, y: f32) -> f32 {
+    match hash & 7 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y
+    }
+}
@@ -0,0 +1,175 @@
+// Colour schemes for the trails cells leave behind.  Each palette maps a
+// cell's linger level (0 = long dead, 9 = alive) to a background colour.
+
+use std::fs;
+use std::path::Path;
+
+use tcod::colors as color;
+use tcod::Color;
+
+#[derive(Copy, Clone)]
+pub struct Palette {
+    pub colors: [Color; 10]
+}
+
+pub const FIRE: Palette = Palette { colors: [
+    color::BLACK,
+    color::DARKEST_RED,
+    color::DARKER_RED,
+    color::DARK_RED,
+    color::RED,
+    color::FLAME,
+    color::ORANGE,
+    color::AMBER,
+    color::YELLOW,
+    color::LIGHT_YELLOW
+] };
+
+pub const ICE: Palette = Palette { colors: [
+    color::BLACK,
+    color::DARKEST_BLUE,
+    color::DARKER_BLUE,
+    color::DARKER_AZURE,
+    color::DARK_AZURE,
+    color::AZURE,
+    color::LIGHT_AZURE,
+    color::LIGHTER_AZURE,
+    color::LIGHTEST_AZURE,
+    color::LIGHTEST_CYAN
+] };
+
+pub const GREYSCALE: Palette = Palette { colors: [
+    color::BLACK,
+    Color { r: 15, g: 15, b: 15 },
+    color::DARKEST_GREY,
+    color::DARKER_GREY,
+    color::DARK_GREY,
+    color::GREY,
+    color::LIGHT_GREY,
+    color::LIGHTER_GREY,
+    color::LIGHTEST_GREY,
+    color::WHITE
+] };
+
+pub const MATRIX: Palette = Palette { colors: [
+    color::BLACK,
+    Color { r: 0, g: 31, b: 0 },
+    color::DARKEST_GREEN,
+    Color { r: 0, g: 95, b: 0 },
+    color::DARKER_GREEN,
+    Color { r: 0, g: 159, b: 0 },
+    color::DARK_GREEN,
+    color::GREEN,
+    color::LIGHT_GREEN,
+    color::LIGHTER_GREEN
+] };
+
+// The built-in palettes, in the order the palette key cycles through them
+pub const PALETTES: [(&str, Palette); 4] = [
+    ("fire", FIRE),
+    ("ice", ICE),
+    ("greyscale", GREYSCALE),
+    ("matrix", MATRIX)
+];
+
+impl Palette {
+    // The same gradient with its colour channels rotated, giving a different
+    // hue at the same brightnesses.  Used for the second side in Immigration.
+    pub fn tinted(&self) -> Palette {
+        let mut colors = self.colors;
+        for c in colors.iter_mut() {
+            *c = Color { r: c.g, g: c.b, b: c.r };
+        }
+        Palette { colors }
+    }
+
+    // Colour of a shade from 0 to 19: the first ten are this palette, the
+    // rest its tinted twin
+    pub fn shade(&self, shade: usize) -> Color {
+        if shade < 10 { self.colors[shade] } else { self.tinted().colors[shade - 10] }
+    }
+
+    // Every shade, in order, as one flat list
+    pub fn shades(&self) -> Vec<Color> {
+        self.colors.iter().chain(self.tinted().colors.iter()).cloned().collect()
+    }
+
+    // Position of a built-in palette in PALETTES
+    pub fn find(name: &str) -> Result<usize, String> {
+        PALETTES.iter().position(|&(n, _)| n == name)
+            .ok_or_else(|| format!("unknown palette '{}'", name))
+    }
+
+    // Load a palette from a file of ten `r,g,b` lines, darkest (linger 0)
+    // first.  Blank lines and lines starting with '#' are ignored.
+    pub fn from_file(path: &Path) -> Result<Palette, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+        Palette::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn parse(text: &str) -> Result<Palette, String> {
+        let lines: Vec<(usize, &str)> = text.lines().enumerate()
+            .map(|(n, l)| (n + 1, l.trim()))
+            .filter(|&(_, l)| !l.is_empty() && !l.starts_with('#'))
+            .collect();
+        if lines.len() != 10 {
+            return Err(format!("expected 10 colours, found {}", lines.len()));
+        }
+        let mut colors = [color::BLACK; 10];
+        for (c, &(n, line)) in colors.iter_mut().zip(lines.iter()) {
+            let parts = line.split(',')
+                .map(|p| p.trim().parse::<u32>()
+                     .map_err(|_| format!("line {}: '{}' is not a number", n, p.trim())))
+                .collect::<Result<Vec<u32>, String>>()?;
+            if parts.len() != 3 {
+                return Err(format!("line {}: expected r,g,b", n));
+            }
+            if parts.iter().any(|&v| v > 255) {
+                return Err(format!("line {}: components must be between 0 and 255", n));
+            }
+            *c = Color { r: parts[0] as u8, g: parts[1] as u8, b: parts[2] as u8 };
+        }
+        Ok(Palette { colors })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRADIENT: &str = "# dark to light
+0,0,0
+10, 20, 30
+20,40,60
+30,60,90
+40,80,120
+
+50,100,150
+60,120,180
+70,140,210
+80,160,240
+255,255,255
+";
+
+    #[test]
+    fn parses_ten_colours() {
+        let palette = Palette::parse(GRADIENT).unwrap();
+        assert_eq!(palette.colors[0], color::BLACK);
+        assert_eq!(palette.colors[1], Color { r: 10, g: 20, b: 30 });
+        assert_eq!(palette.colors[9], color::WHITE);
+    }
+
+    #[test]
+    fn rejects_wrong_number_of_colours() {
+        assert!(Palette::parse("0,0,0\n1,1,1\n").is_err());
+        assert!(Palette::parse(&format!("{}0,0,0\n", GRADIENT)).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_components() {
+        assert!(Palette::parse(&GRADIENT.replace("10, 20, 30", "10,20,300")).is_err());
+        assert!(Palette::parse(&GRADIENT.replace("10, 20, 30", "10,20")).is_err());
+        assert!(Palette::parse(&GRADIENT.replace("10, 20, 30", "10,-20,30")).is_err());
+    }
+}
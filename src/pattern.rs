@@ -0,0 +1,308 @@
+use map::{BoundaryMode, Map};
+use patterns;
+
+// A pattern parsed from one of the Life file formats.  `cells` holds the
+// coordinates of the live cells relative to the top-left of the pattern's
+// `width` x `height` bounding box.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pattern {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<(usize, usize)>
+}
+
+// How a pattern is turned before it's stamped: rotated clockwise by 0, 90,
+// 180 or 270 degrees, or mirrored left to right (FlipX) or top to bottom
+// (FlipY)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Orientation {
+    R0,
+    R90,
+    R180,
+    R270,
+    FlipX,
+    FlipY
+}
+
+// A side of the map, e.g. for inject_glider to fire from
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right
+}
+
+impl Orientation {
+    // The orientation after this one, so a key can cycle through them all
+    pub fn next(self) -> Orientation {
+        match self {
+            Orientation::R0 => Orientation::R90,
+            Orientation::R90 => Orientation::R180,
+            Orientation::R180 => Orientation::R270,
+            Orientation::R270 => Orientation::FlipX,
+            Orientation::FlipX => Orientation::FlipY,
+            Orientation::FlipY => Orientation::R0
+        }
+    }
+}
+
+impl Pattern {
+    // The same pattern turned to the given orientation, with its cells back
+    // in row-major order
+    pub fn oriented(&self, orientation: Orientation) -> Pattern {
+        let (w, h) = (self.width, self.height);
+        let turn = |(x, y): (usize, usize)| match orientation {
+            Orientation::R0 => (x, y),
+            Orientation::R90 => (h - 1 - y, x),
+            Orientation::R180 => (w - 1 - x, h - 1 - y),
+            Orientation::R270 => (y, w - 1 - x),
+            Orientation::FlipX => (w - 1 - x, y),
+            Orientation::FlipY => (x, h - 1 - y)
+        };
+        let (width, height) = match orientation {
+            Orientation::R90 | Orientation::R270 => (h, w),
+            _ => (w, h)
+        };
+        let mut cells: Vec<(usize, usize)> = self.cells.iter().map(|&c| turn(c)).collect();
+        cells.sort_by_key(|&(x, y)| (y, x));
+        Pattern { width, height, cells }
+    }
+
+    // The same pattern with any empty rows and columns around it removed
+    pub fn trimmed(&self) -> Pattern {
+        let x0 = self.cells.iter().map(|&(x, _)| x).min().unwrap_or(0);
+        let y0 = self.cells.iter().map(|&(_, y)| y).min().unwrap_or(0);
+        let x1 = self.cells.iter().map(|&(x, _)| x + 1).max().unwrap_or(0);
+        let y1 = self.cells.iter().map(|&(_, y)| y + 1).max().unwrap_or(0);
+        Pattern {
+            width: x1 - x0,
+            height: y1 - y0,
+            cells: self.cells.iter().map(|&(x, y)| (x - x0, y - y0)).collect()
+        }
+    }
+}
+
+impl Map {
+    // Clear the pattern's bounding box at (x, y) and bring its cells to life.
+    // Patterns that don't fit on the map are rejected and the map is left
+    // untouched.
+    pub fn stamp(&mut self, pattern: &Pattern, x: usize, y: usize) -> Result<(), String> {
+        // Subtracting, since a header can claim any size
+        if pattern.width > self.width || x > self.width - pattern.width
+            || pattern.height > self.height || y > self.height - pattern.height {
+            return Err(format!("{}x{} pattern doesn't fit at ({}, {})",
+                               pattern.width, pattern.height, x, y));
+        }
+        for i in x..(x + pattern.width) {
+            for j in y..(y + pattern.height) {
+                self.set_alive(i, j, false);
+            }
+        }
+        for &(i, j) in &pattern.cells {
+            self.set_alive(x + i, y + j, true);
+        }
+        Ok(())
+    }
+
+    // Stamp an RLE pattern, trimmed to its live cells and turned to
+    // `orientation`, in the middle of the view_w x view_h viewport.  On an
+    // edge that wraps the pattern can wrap too; otherwise it's pushed back
+    // onto the map.  Patterns bigger than the viewport still get centred on
+    // it, but patterns bigger than the map are rejected.
+    pub fn stamp_centered(&mut self, rle: &str, view_w: i32, view_h: i32,
+                          orientation: Orientation) -> Result<(), String> {
+        let pattern = Pattern::from_rle(rle)?.trimmed().oriented(orientation);
+        let (x, y) = (self.o_x + view_w / 2, self.o_y + view_h / 2);
+        self.stamp_around(&pattern, x, y)
+    }
+
+    // Stamp a pattern, clearing its bounding box first, centred as near to
+    // map position (x, y) as it can be: wrapping where the edge wraps, and
+    // pushed back onto the map elsewhere
+    pub fn stamp_around(&mut self, pattern: &Pattern, x: i32, y: i32) -> Result<(), String> {
+        if pattern.width > self.width || pattern.height > self.height {
+            return Err(format!("{}x{} pattern is larger than the {}x{} map",
+                               pattern.width, pattern.height, self.width, self.height));
+        }
+        let (wrap_x, wrap_y) = match self.boundary {
+            BoundaryMode::Wrap => (true, true),
+            BoundaryMode::CylinderX => (true, false),
+            BoundaryMode::Dead | BoundaryMode::Mirror => (false, false)
+        };
+        let x = place(x, pattern.width, self.width, wrap_x);
+        let y = place(y, pattern.height, self.height, wrap_y);
+        // Placement keeps non-wrapping axes in bounds, so the modulo only
+        // matters where the pattern wraps
+        for i in 0..pattern.width {
+            for j in 0..pattern.height {
+                self.set_alive((x + i) % self.width, (y + j) % self.height, false);
+            }
+        }
+        for &(i, j) in &pattern.cells {
+            self.set_alive((x + i) % self.width, (y + j) % self.height, true);
+        }
+        Ok(())
+    }
+
+    // Stamp a glider just inside `from`, `at` cells along it (clamped so it
+    // fits), heading into the map and diagonally towards the middle.  The
+    // glider as written heads down and right, so it's mirrored to suit.
+    pub fn inject_glider(&mut self, from: Edge, at: usize) -> Result<(), String> {
+        let glider = Pattern::from_rle(patterns::GLIDER)?;
+        let (gw, gh) = (glider.width, glider.height);
+        if self.width < gw || self.height < gh {
+            return Err(format!("the {}x{} map is too small for a glider", self.width, self.height));
+        }
+        let (max_x, max_y) = (self.width - gw, self.height - gh);
+        let (x, y) = match from {
+            Edge::Top => (at.min(max_x), 0),
+            Edge::Bottom => (at.min(max_x), max_y),
+            Edge::Left => (0, at.min(max_y)),
+            Edge::Right => (max_x, at.min(max_y))
+        };
+        let right = match from {
+            Edge::Left => true,
+            Edge::Right => false,
+            Edge::Top | Edge::Bottom => x < self.width / 2
+        };
+        let down = match from {
+            Edge::Top => true,
+            Edge::Bottom => false,
+            Edge::Left | Edge::Right => y < self.height / 2
+        };
+        let orientation = match (right, down) {
+            (true, true) => Orientation::R0,
+            (false, true) => Orientation::FlipX,
+            (true, false) => Orientation::FlipY,
+            (false, false) => Orientation::R180
+        };
+        self.stamp(&glider.oriented(orientation), x, y)
+    }
+}
+
+// Start of a `size` long span centred on `centre`, along an axis `len` cells
+// long
+fn place(centre: i32, size: usize, len: usize, wraps: bool) -> usize {
+    let start = centre - size as i32 / 2;
+    if wraps {
+        start.rem_euclid(len as i32) as usize
+    } else {
+        start.clamp(0, (len - size) as i32) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use patterns;
+    use rules::{Ruleset, DEFAULT_RULE};
+
+    fn conway() -> Map {
+        Map::new(100, 50, Ruleset::parse(DEFAULT_RULE).unwrap())
+    }
+
+    #[test]
+    fn trims_empty_margins() {
+        let pattern = Pattern { width: 5, height: 5, cells: vec![(1, 2), (3, 3)] };
+        assert_eq!(pattern.trimmed(), Pattern { width: 3, height: 2, cells: vec![(0, 0), (2, 1)] });
+    }
+
+    #[test]
+    fn orientations_turn_the_bounding_box() {
+        let pattern = Pattern::from_rle(patterns::LWSS).unwrap();
+        let turned = pattern.oriented(Orientation::R90);
+        assert_eq!((turned.width, turned.height), (pattern.height, pattern.width));
+        let mut back = pattern.clone();
+        for _ in 0..4 {
+            back = back.oriented(Orientation::R90);
+        }
+        assert_eq!(back, pattern);
+        assert_eq!(pattern.oriented(Orientation::FlipX).oriented(Orientation::FlipX), pattern);
+        assert_eq!(pattern.oriented(Orientation::FlipX).oriented(Orientation::FlipY),
+                   pattern.oriented(Orientation::R180));
+    }
+
+    #[test]
+    fn rotated_gliders_still_glide() {
+        let mut map = conway();
+        map.load_rle(patterns::GLIDER, 50, 20, Orientation::R180).unwrap();
+        let start: Vec<(usize, usize)> = map.live_iter().collect();
+        for _ in 0..4 {
+            map.tick();
+        }
+        // Turned around, it heads up and to the left instead
+        let moved: Vec<(usize, usize)> = start.iter().map(|&(x, y)| (x - 1, y - 1)).collect();
+        assert_eq!(map.live_iter().collect::<Vec<_>>(), moved);
+    }
+
+    #[test]
+    fn injected_gliders_head_inwards() {
+        let cases = [(Edge::Left, 10, (1, 1)), (Edge::Right, 40, (-1, -1)), (Edge::Top, 80, (-1, 1)),
+                     (Edge::Bottom, 200, (-1, -1))];
+        for &(edge, at, (dx, dy)) in &cases {
+            let mut map = conway();
+            map.inject_glider(edge, at).unwrap();
+            let (x0, y0, _, _) = map.bounding_box().unwrap();
+            for _ in 0..8 {
+                map.tick();
+            }
+            let (x1, y1, _, _) = map.bounding_box().unwrap();
+            assert_eq!((x1 as i32 - x0 as i32, y1 as i32 - y0 as i32), (2 * dx, 2 * dy), "{:?}", edge);
+        }
+    }
+
+    #[test]
+    fn stamps_in_the_middle_of_the_view() {
+        let mut map = conway();
+        map.o_x = 20;
+        map.o_y = 10;
+        map.stamp_centered(patterns::GLIDER, 40, 20, Orientation::R0).unwrap();
+        assert_eq!(map.bounding_box(), Some((39, 19, 41, 21)));
+    }
+
+    #[test]
+    fn stamps_are_kept_on_the_map() {
+        let mut map = conway();
+        map.o_x = 90;
+        map.o_y = 45;
+        map.stamp_centered(patterns::GLIDER, 20, 10, Orientation::R0).unwrap();
+        assert_eq!(map.bounding_box(), Some((97, 47, 99, 49)));
+    }
+
+    #[test]
+    fn stamps_wrap_on_a_torus() {
+        let mut map = conway();
+        map.boundary = BoundaryMode::Wrap;
+        map.o_x = 90;
+        map.o_y = 45;
+        map.stamp_centered(patterns::GLIDER, 20, 10, Orientation::R0).unwrap();
+        assert_eq!(map.live_cells(), 5);
+        // Centred on (100, 50), i.e. straddling the corner
+        assert!(map.cell(0, 49).alive && map.cell(99, 1).alive && map.cell(1, 1).alive);
+    }
+
+    #[test]
+    fn stamps_around_a_point_by_the_edge() {
+        let glider = Pattern::from_rle(patterns::GLIDER).unwrap();
+        let mut map = conway();
+        map.stamp_around(&glider, 99, 0).unwrap();
+        assert_eq!(map.bounding_box(), Some((97, 0, 99, 2)));
+        let mut map = conway();
+        map.boundary = BoundaryMode::Wrap;
+        map.stamp_around(&glider, 99, 0).unwrap();
+        assert_eq!(map.live_cells(), 5);
+        assert!(map.cell(99, 49).alive && map.cell(0, 0).alive && map.cell(0, 1).alive);
+    }
+
+    #[test]
+    fn rejects_patterns_larger_than_the_map() {
+        let mut map = Map::new(20, 20, Ruleset::parse(DEFAULT_RULE).unwrap());
+        assert!(map.stamp_centered(patterns::GOSPER_GLIDER_GUN, 20, 20, Orientation::R0).is_err());
+        assert_eq!(map.live_cells(), 0);
+        // Bigger than the view is fine though
+        let mut map = conway();
+        assert!(map.stamp_centered(patterns::GOSPER_GLIDER_GUN, 10, 5, Orientation::R0).is_ok());
+        assert_eq!(map.live_cells(), 36);
+    }
+}
@@ -0,0 +1,34 @@
+// A few well-known patterns, in RLE, for stamping onto the board
+
+pub const GLIDER: &str = "#N Glider
+x = 3, y = 3, rule = B3/S23
+bo$2bo$3o!";
+
+pub const LWSS: &str = "#N Lightweight spaceship
+x = 5, y = 4, rule = B3/S23
+bo2bo$o4b$o3bo$4o!";
+
+pub const GOSPER_GLIDER_GUN: &str = "#N Gosper glider gun
+#C The first known gun
+x = 36, y = 9, rule = B3/S23
+24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4b
+obo$10bo5bo7bo$11bo3bo$12b2o!";
+
+pub const PULSAR: &str = "#N Pulsar
+x = 13, y = 13, rule = B3/S23
+2b3o3b3o2b2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2b2$2b3o3b3o2b$o4bobo
+4bo$o4bobo4bo$o4bobo4bo2$2b3o3b3o!";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pattern::Pattern;
+
+    #[test]
+    fn library_patterns_parse() {
+        let counts = [(GLIDER, 5), (LWSS, 9), (GOSPER_GLIDER_GUN, 36), (PULSAR, 48)];
+        for &(rle, count) in &counts {
+            assert_eq!(Pattern::from_rle(rle).unwrap().cells.len(), count);
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use std::path::Path;
+
+use image::imageops::{self, FilterType};
+use image::GrayImage;
+
+use map::Map;
+
+impl Map {
+    // Seed the board from a picture, stretched to fit the map: a cell starts
+    // alive where its pixel is darker than `threshold`, so dark shapes on a
+    // light background come out as live cells.  Clears the board first, like
+    // init_noise_seeded.
+    pub fn init_from_image(&mut self, path: &Path, threshold: u8) -> Result<(), String> {
+        let img = image::open(path).map_err(|e| format!("couldn't load {}: {}", path.display(), e))?;
+        info!("seeding {}x{} board from {}", self.width, self.height, path.display());
+        self.init_from_gray(&img.to_luma8(), threshold);
+        Ok(())
+    }
+
+    fn init_from_gray(&mut self, img: &GrayImage, threshold: u8) {
+        let scaled = imageops::resize(img, self.width as u32, self.height as u32, FilterType::Triangle);
+        self.clear();
+        for (x, y, pixel) in scaled.enumerate_pixels() {
+            if pixel[0] < threshold {
+                self.set_alive(x as usize, y as usize, true);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+    use rules::{Ruleset, DEFAULT_RULE};
+
+    #[test]
+    fn dark_pixels_come_alive() {
+        // A black left half on white, at twice the map's size
+        let img = GrayImage::from_fn(40, 20, |x, _| Luma([if x < 20 { 0 } else { 255 }]));
+        let mut map = Map::new(20, 10, Ruleset::parse(DEFAULT_RULE).unwrap());
+        map.set_alive(19, 0, true);
+        map.init_from_gray(&img, 128);
+        assert_eq!(map.live_cells(), 10 * 10);
+        assert_eq!(map.bounding_box(), Some((0, 0, 9, 9)));
+        // Nothing is darker than black
+        map.init_from_gray(&img, 0);
+        assert_eq!(map.live_cells(), 0);
+    }
+
+    #[test]
+    fn missing_files_are_reported() {
+        let mut map = Map::new(20, 10, Ruleset::parse(DEFAULT_RULE).unwrap());
+        assert!(map.init_from_image(Path::new("no-such-picture.png"), 128).is_err());
+    }
+}
@@ -0,0 +1,226 @@
+use map::Map;
+use pattern::{Orientation, Pattern};
+
+impl Pattern {
+    // Parse a pattern in the RLE format: `#` comment lines, an
+    // `x = W, y = H` header, then a body of run-length encoded `b` (dead) and
+    // `o` (alive) cells with `$` ending a row and `!` ending the pattern.
+    pub fn from_rle(rle: &str) -> Result<Pattern, String> {
+        let mut lines = rle.lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('#'));
+        let (width, height) = match lines.next() {
+            Some(header) => parse_header(header)?,
+            None => return Err("missing RLE header".to_string())
+        };
+
+        let mut cells = Vec::new();
+        let (mut x, mut y) = (0usize, 0usize);
+        let mut run = 0usize;
+        'body: for line in lines {
+            for c in line.chars() {
+                match c {
+                    '0'..='9' => {
+                        run = run.checked_mul(10)
+                            .and_then(|r| r.checked_add(c.to_digit(10).unwrap() as usize))
+                            .ok_or("run count too large")?;
+                    },
+                    'b' | 'o' | '$' => {
+                        let n = if run == 0 { 1 } else { run };
+                        run = 0;
+                        if c == '$' {
+                            x = 0;
+                            y = y.checked_add(n).ok_or("run count too large")?;
+                            continue;
+                        }
+                        let end = x.checked_add(n).ok_or("run count too large")?;
+                        if end > width || y >= height {
+                            return Err(format!("pattern body exceeds the {}x{} header", width, height));
+                        }
+                        if c == 'o' {
+                            for i in x..end { cells.push((i, y)) };
+                        }
+                        x = end;
+                    },
+                    '!' => break 'body,
+                    c if c.is_whitespace() => {},
+                    _ => return Err(format!("unexpected '{}' in RLE body", c))
+                }
+            }
+        }
+        Ok(Pattern { width, height, cells })
+    }
+}
+
+// Read the width and height out of a line like `x = 3, y = 3, rule = B3/S23`
+fn parse_header(header: &str) -> Result<(usize, usize), String> {
+    let mut width = None;
+    let mut height = None;
+    for field in header.split(',') {
+        let mut kv = field.splitn(2, '=');
+        let key = kv.next().unwrap_or("").trim();
+        let value = kv.next().unwrap_or("").trim();
+        match key {
+            "x" => width = value.parse().ok(),
+            "y" => height = value.parse().ok(),
+            _ => {}
+        }
+    }
+    match (width, height) {
+        (Some(w), Some(h)) => Ok((w, h)),
+        _ => Err(format!("invalid RLE header '{}'", header))
+    }
+}
+
+impl Map {
+    // Load an RLE pattern, turned to `orientation`, with its top-left corner
+    // at the given origin
+    pub fn load_rle(&mut self, rle: &str, origin_x: usize, origin_y: usize,
+                    orientation: Orientation) -> Result<(), String> {
+        let pattern = Pattern::from_rle(rle)?.oriented(orientation);
+        self.stamp(&pattern, origin_x, origin_y)
+    }
+
+    // Serialize the live cells as RLE, trimmed to their bounding box
+    pub fn to_rle(&self) -> String {
+        self.region_to_rle(0, 0, self.width - 1, self.height - 1)
+    }
+
+    // Like to_rle, but only for the live cells inside the rectangle with
+    // corners (x0, y0) and (x1, y1) inclusive, clamped to the map
+    pub fn region_to_rle(&self, x0: usize, y0: usize, x1: usize, y1: usize) -> String {
+        let (x1, y1) = (x1.min(self.width - 1), y1.min(self.height - 1));
+        let bbox = self.live_iter()
+            .filter(|&(x, y)| x >= x0 && x <= x1 && y >= y0 && y <= y1)
+            .fold(None, |bbox, (x, y)| Some(match bbox {
+                None => (x, y, x, y),
+                Some((a, b, c, d)) => (x.min(a), y.min(b), x.max(c), y.max(d))
+            }));
+        let (x0, y0, x1, y1) = match bbox {
+            Some(bbox) => bbox,
+            None => return format!("x = 0, y = 0, rule = {}\n!\n", self.rule)
+        };
+        let mut out = format!("x = {}, y = {}, rule = {}\n", x1 - x0 + 1, y1 - y0 + 1, self.rule);
+
+        let mut tokens = Vec::new();
+        let mut last_row = None;
+        for y in y0..(y1 + 1) {
+            // Collect the runs for this row, dropping trailing dead cells
+            let mut runs: Vec<(usize, char)> = Vec::new();
+            for x in x0..(x1 + 1) {
+                let c = if self.cell(x, y).alive { 'o' } else { 'b' };
+                match runs.last_mut() {
+                    Some(run) if run.1 == c => run.0 += 1,
+                    _ => runs.push((1, c))
+                }
+            }
+            if runs.last().map(|r| r.1) == Some('b') { runs.pop(); }
+            if runs.is_empty() { continue };
+
+            if let Some(prev) = last_row { tokens.push(run_token(y - prev, '$')) };
+            for (n, c) in runs { tokens.push(run_token(n, c)) };
+            last_row = Some(y);
+        }
+        tokens.push("!".to_string());
+
+        // Keep lines under the customary 70 characters
+        let mut line = String::new();
+        for token in tokens {
+            if line.len() + token.len() > 70 {
+                out.push_str(&line);
+                out.push('\n');
+                line.clear();
+            }
+            line.push_str(&token);
+        }
+        out.push_str(&line);
+        out.push('\n');
+        out
+    }
+}
+
+fn run_token(n: usize, c: char) -> String {
+    if n == 1 { c.to_string() } else { format!("{}{}", n, c) }
+}
+
+#[cfg(test)]
+mod tests {
+    use map::{Map, MAP_HEIGHT, MAP_WIDTH};
+    use pattern::Orientation;
+    use patterns::GOSPER_GLIDER_GUN;
+    use rules::{Ruleset, DEFAULT_RULE};
+
+    fn conway() -> Map {
+        Map::new(MAP_WIDTH, MAP_HEIGHT, Ruleset::parse(DEFAULT_RULE).unwrap())
+    }
+
+    #[test]
+    fn loads_gosper_glider_gun() {
+        let mut map = conway();
+        map.load_rle(GOSPER_GLIDER_GUN, 10, 10, Orientation::R0).unwrap();
+        assert_eq!(map.live_cells(), 36);
+        // The leftmost block of the gun
+        assert!(map.cell(10, 14).alive && map.cell(11, 14).alive);
+        assert!(map.cell(10, 15).alive && map.cell(11, 15).alive);
+    }
+
+    #[test]
+    fn rejects_patterns_that_dont_fit() {
+        let mut map = conway();
+        let (w, h) = (map.width, map.height);
+        assert!(map.load_rle(GOSPER_GLIDER_GUN, w - 35, 0, Orientation::R0).is_err());
+        assert!(map.load_rle(GOSPER_GLIDER_GUN, 0, h - 8, Orientation::R0).is_err());
+        assert_eq!(map.live_cells(), 0);
+    }
+
+    #[test]
+    fn exports_trimmed_rle() {
+        let mut map = conway();
+        // A glider well away from the origin
+        map.load_rle("x = 3, y = 3\nbo$2bo$3o!", 50, 20, Orientation::R0).unwrap();
+        assert_eq!(map.to_rle(), "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n");
+    }
+
+    #[test]
+    fn rle_round_trips() {
+        let mut map = conway();
+        map.load_rle(GOSPER_GLIDER_GUN, 10, 10, Orientation::R0).unwrap();
+        // Spread some extra cells around to exercise blank rows and columns
+        map.toggle(70, 30);
+        map.toggle(5, 40);
+        let rle = map.to_rle();
+
+        let mut copy = conway();
+        copy.load_rle(&rle, 5, 10, Orientation::R0).unwrap();
+        for x in 0..map.width {
+            for y in 0..map.height {
+                assert_eq!(map.cell(x, y).alive, copy.cell(x, y).alive, "cell ({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn saves_just_the_region() {
+        let mut map = conway();
+        map.load_rle("x = 3, y = 3\nbo$2bo$3o!", 50, 20, Orientation::R0).unwrap();
+        map.toggle(60, 21);
+        map.toggle(48, 30);
+        assert_eq!(map.region_to_rle(45, 15, 55, 25), "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n");
+        assert_eq!(map.region_to_rle(0, 0, 10, 10), "x = 0, y = 0, rule = B3/S23\n!\n");
+    }
+
+    #[test]
+    fn rejects_malformed_rle() {
+        let mut map = conway();
+        assert!(map.load_rle("3o!", 0, 0, Orientation::R0).is_err());
+        assert!(map.load_rle("x = 3, y = 1\n4o!", 0, 0, Orientation::R0).is_err());
+        assert!(map.load_rle("x = 3, y = 1\n3q!", 0, 0, Orientation::R0).is_err());
+        // Runs too long to count
+        assert_eq!(map.load_rle("x = 3, y = 1\n99999999999999999999o!", 0, 0, Orientation::R0),
+                   Err("run count too large".to_string()));
+        assert!(map.load_rle("x = 3, y = 1\no18446744073709551615o!", 0, 0, Orientation::R0).is_err());
+        // Or a header too big for the map
+        assert!(map.load_rle("x = 18446744073709551615, y = 1\no!", 1, 0, Orientation::R0).is_err());
+        assert!(map.load_rle("x = 1, y = 18446744073709551615\no!", 0, 1, Orientation::R0).is_err());
+    }
+}
@@ -0,0 +1,42 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Small xorshift generator.  We only need cheap, repeatable randomness for
+// seeding boards, so there's no point pulling in a full RNG crate.
+pub struct Rng {
+    state: u64
+}
+
+impl Rng {
+    pub fn new(seed: u32) -> Rng {
+        // Spread the seed out so that nearby seeds give unrelated streams, and
+        // make sure the state is never zero (xorshift would get stuck there).
+        let state = (seed as u64 ^ 0x9E37_79B9_7F4A_7C15).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        Rng { state: if state == 0 { 1 } else { state } }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state >> 32) as u32
+    }
+}
+
+// A number in [0, 1) for the cell at row-major `index` in `generation`, the
+// same every time for the same seed.  Each roll is worked out on its own
+// rather than drawn from a stream, so cells can be visited in any order, or
+// in parallel, and still see the same numbers as a row-major pass would.
+pub fn roll(seed: u32, generation: u64, index: usize) -> f64 {
+    // splitmix64's finaliser over all three
+    let mut z = (seed as u64) << 32 ^ generation.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ index as u64;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+// A seed that differs from run to run
+pub fn time_seed() -> u32 {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    now.subsec_nanos() ^ now.as_secs() as u32
+}
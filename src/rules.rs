@@ -0,0 +1,398 @@
+use std::fmt;
+
+// Standard Conway rules
+pub const DEFAULT_RULE: &str = "B3/S23";
+
+// Well-known rules that can be given by name instead of in B/S notation, in
+// the order the frontend cycles through them
+pub const PRESETS: [(&str, &str); 6] = [
+    ("life", DEFAULT_RULE),
+    ("highlife", "B36/S23"),
+    ("seeds", "B2/S"),
+    ("lifewithoutdeath", "B3/S012345678"),
+    ("daynight", "B3678/S34678"),
+    ("antilife", "B0123478/S01234678")
+];
+
+// Birth/survival rule in B/S notation, e.g. "B3/S23" for Conway's Life.
+// Each list holds the neighbour counts that cause a dead cell to be born or
+// a live cell to survive.
+//
+// An optional third part, e.g. "B2/S345/C4", makes it a Generations rule with
+// that many states: cells that fail to survive spend the states between alive
+// and dead dying, and can't be reborn until they're fully dead.
+//
+// Either half can instead list conditions on how many live neighbours are
+// orthogonal to the cell (in its row or column) and how many diagonal, e.g.
+// "B1:2/S23:*" for births with one orthogonal and two diagonal neighbours,
+// and survival with two or three orthogonal ones however many diagonal.
+// Each side of a ':' is a set of counts from 0 to 4, or '*' for any.  Such a
+// rule keeps both halves in `split` and leaves `birth` and `survival` empty.
+//
+// Births and survivals can also be made to only happen with some
+// probability, drawn from the map's seeded rolls.  The notation has no way to
+// write these, so they're set separately and left out of to_string().
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ruleset {
+    pub birth: Vec<u8>,
+    pub survival: Vec<u8>,
+    pub split: Option<SplitCounts>,
+    // 2 for ordinary two-state rules
+    pub states: u8,
+    // Chance that a cell with the right count is born, or survives; 1 for a
+    // deterministic rule
+    pub birth_prob: f32,
+    pub survival_prob: f32
+}
+
+impl Ruleset {
+    // Parse a rule in B/S notation, or the name of one of the PRESETS
+    pub fn parse(rule: &str) -> Result<Ruleset, String> {
+        if let Some(&(_, preset)) = PRESETS.iter().find(|p| p.0.eq_ignore_ascii_case(rule.trim())) {
+            return Ruleset::parse(preset);
+        }
+        let parts: Vec<&str> = rule.trim().split('/').collect();
+        if parts.len() != 2 && parts.len() != 3 {
+            return Err(format!("rule '{}' must have the form B<digits>/S<digits>[/C<states>]", rule));
+        }
+        let states = match parts.get(2) {
+            Some(part) => Ruleset::parse_states(part)?,
+            None => 2
+        };
+        let mut ruleset = Ruleset { birth: Vec::new(), survival: Vec::new(), split: None, states,
+                                    birth_prob: 1.0, survival_prob: 1.0 };
+        if parts[0].contains(':') || parts[1].contains(':') {
+            let split = SplitCounts {
+                birth: SplitCounts::parse(parts[0], 'B')?,
+                survival: SplitCounts::parse(parts[1], 'S')?
+            };
+            // The background of a B0 rule is handled from the plain counts
+            if split.birth & 1 != 0 {
+                return Err(format!("rule '{}' can't give birth with no neighbours", rule));
+            }
+            ruleset.split = Some(split);
+        } else {
+            ruleset.birth = Ruleset::parse_counts(parts[0], 'B')?;
+            ruleset.survival = Ruleset::parse_counts(parts[1], 'S')?;
+        }
+        Ok(ruleset)
+    }
+
+    // Whether a dead cell with `orthogonal` and `diagonal` live neighbours is
+    // born
+    pub fn born(&self, orthogonal: i32, diagonal: i32) -> bool {
+        match self.split {
+            Some(split) => split_allows(split.birth, orthogonal, diagonal),
+            None => counts_allow(&self.birth, orthogonal + diagonal)
+        }
+    }
+
+    // Whether a live cell with `orthogonal` and `diagonal` live neighbours
+    // survives
+    pub fn survives(&self, orthogonal: i32, diagonal: i32) -> bool {
+        match self.split {
+            Some(split) => split_allows(split.survival, orthogonal, diagonal),
+            None => counts_allow(&self.survival, orthogonal + diagonal)
+        }
+    }
+
+    // The name of the preset this rule is, if any.  The odds of a stochastic
+    // rule aren't part of it.
+    pub fn preset_name(&self) -> Option<&'static str> {
+        let notation = self.to_string();
+        PRESETS.iter()
+            .find(|p| Ruleset::parse(p.1).map(|r| r.to_string()).as_ref() == Ok(&notation))
+            .map(|p| p.0)
+    }
+
+    // The preset after this one in PRESETS, or the first if this isn't one
+    pub fn next_preset(&self) -> Ruleset {
+        let next = match self.preset_name() {
+            Some(name) => (PRESETS.iter().position(|p| p.0 == name).unwrap_or(0) + 1) % PRESETS.len(),
+            None => 0
+        };
+        Ruleset::parse(PRESETS[next].1).expect("presets are valid rules")
+    }
+
+    pub fn is_stochastic(&self) -> bool {
+        self.birth_prob < 1.0 || self.survival_prob < 1.0
+    }
+
+    // Parse the "C<n>" state count of a Generations rule
+    fn parse_states(part: &str) -> Result<u8, String> {
+        let mut chars = part.chars();
+        match chars.next() {
+            Some('C') | Some('c') => {},
+            _ => return Err(format!("expected 'C' at the start of '{}'", part))
+        }
+        match chars.as_str().parse::<u8>() {
+            Ok(n) if n >= 2 => Ok(n),
+            _ => Err(format!("invalid state count in '{}', expected 2 to 255", part))
+        }
+    }
+
+    // Parse one half of the rule, e.g. "B36", into its neighbour counts.
+    fn parse_counts(part: &str, prefix: char) -> Result<Vec<u8>, String> {
+        let mut chars = part.chars();
+        match chars.next() {
+            Some(c) if c.to_ascii_uppercase() == prefix => {},
+            _ => return Err(format!("expected '{}' at the start of '{}'", prefix, part))
+        }
+        let mut counts = Vec::new();
+        for c in chars {
+            match c.to_digit(10) {
+                Some(n) if n <= 8 => {
+                    if !counts.contains(&(n as u8)) { counts.push(n as u8) };
+                },
+                _ => return Err(format!("invalid neighbour count '{}' in '{}'", c, part))
+            }
+        }
+        counts.sort();
+        Ok(counts)
+    }
+}
+
+fn counts_allow(counts: &[u8], n: i32) -> bool {
+    counts.iter().any(|&c| c as i32 == n)
+}
+
+fn split_allows(mask: u32, orthogonal: i32, diagonal: i32) -> bool {
+    (0..SPLIT_COUNTS).contains(&orthogonal) && (0..SPLIT_COUNTS).contains(&diagonal)
+        && mask & 1 << (orthogonal * SPLIT_COUNTS + diagonal) != 0
+}
+
+// Orthogonal and diagonal neighbours each number 0 to 4
+const SPLIT_COUNTS: i32 = 5;
+// Every count, as a set of them
+const ANY_COUNT: u32 = (1 << SPLIT_COUNTS) - 1;
+
+// The birth and survival conditions of a rule written with ':'.  Bit
+// 5 * o + d of each mask is set if o orthogonal and d diagonal live
+// neighbours will do.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SplitCounts {
+    pub birth: u32,
+    pub survival: u32
+}
+
+impl SplitCounts {
+    // Parse one half of a rule, e.g. "B1:2,3:*".  A half written as plain
+    // counts, e.g. "S23", allows every split adding up to one of them.
+    fn parse(part: &str, prefix: char) -> Result<u32, String> {
+        if !part.contains(':') {
+            let counts = Ruleset::parse_counts(part, prefix)?;
+            let mut mask = 0;
+            for o in 0..SPLIT_COUNTS {
+                for d in 0..SPLIT_COUNTS {
+                    if counts_allow(&counts, o + d) { mask |= 1 << (o * SPLIT_COUNTS + d) };
+                }
+            }
+            return Ok(mask);
+        }
+        let mut chars = part.chars();
+        match chars.next() {
+            Some(c) if c.to_ascii_uppercase() == prefix => {},
+            _ => return Err(format!("expected '{}' at the start of '{}'", prefix, part))
+        }
+        let mut mask = 0;
+        for condition in chars.as_str().split(',') {
+            let sides: Vec<&str> = condition.split(':').collect();
+            if sides.len() != 2 {
+                return Err(format!("expected <orthogonal>:<diagonal> counts, got '{}' in '{}'", condition, part));
+            }
+            let orthogonal = SplitCounts::parse_set(sides[0], part)?;
+            let diagonal = SplitCounts::parse_set(sides[1], part)?;
+            for o in 0..SPLIT_COUNTS {
+                if orthogonal & 1 << o != 0 { mask |= diagonal << (o * SPLIT_COUNTS) };
+            }
+        }
+        Ok(mask)
+    }
+
+    // One side of a condition, as a bit per count
+    fn parse_set(side: &str, part: &str) -> Result<u32, String> {
+        if side == "*" {
+            return Ok(ANY_COUNT);
+        }
+        if side.is_empty() {
+            return Err(format!("missing neighbour counts in '{}'", part));
+        }
+        let mut set = 0;
+        for c in side.chars() {
+            match c.to_digit(10) {
+                Some(n) if (n as i32) < SPLIT_COUNTS => set |= 1 << n,
+                _ => return Err(format!("invalid neighbour count '{}' in '{}', expected 0 to 4 or *", c, part))
+            }
+        }
+        Ok(set)
+    }
+
+    // Write a mask back out, grouping the orthogonal counts that allow the
+    // same diagonal ones
+    fn write(f: &mut fmt::Formatter, mask: u32) -> fmt::Result {
+        let diagonals = |o: i32| mask >> (o * SPLIT_COUNTS) & ANY_COUNT;
+        let set = |f: &mut fmt::Formatter, bits: u32| -> fmt::Result {
+            if bits == ANY_COUNT { return write!(f, "*") };
+            for n in 0..SPLIT_COUNTS {
+                if bits & 1 << n != 0 { write!(f, "{}", n)? };
+            }
+            Ok(())
+        };
+        let mut first = true;
+        for o in 0..SPLIT_COUNTS {
+            let d = diagonals(o);
+            // Already written with an earlier orthogonal count
+            if d == 0 || (0..o).any(|earlier| diagonals(earlier) == d) {
+                continue;
+            }
+            if !first { write!(f, ",")? };
+            first = false;
+            let orthogonal = (o..SPLIT_COUNTS).filter(|&n| diagonals(n) == d).fold(0, |bits, n| bits | 1 << n);
+            set(f, orthogonal)?;
+            write!(f, ":")?;
+            set(f, d)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Ruleset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "B")?;
+        match self.split {
+            Some(split) => SplitCounts::write(f, split.birth)?,
+            None => for n in &self.birth { write!(f, "{}", n)? }
+        }
+        write!(f, "/S")?;
+        match self.split {
+            Some(split) => SplitCounts::write(f, split.survival)?,
+            None => for n in &self.survival { write!(f, "{}", n)? }
+        }
+        if self.states > 2 { write!(f, "/C{}", self.states)? };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        for rule in &["B3/S23", "B36/S23", "B2/S", "B/S012345678"] {
+            assert_eq!(Ruleset::parse(rule).unwrap().to_string(), *rule);
+        }
+        let highlife = Ruleset::parse("B36/S23").unwrap();
+        assert_eq!(highlife.birth, [3, 6]);
+        assert_eq!(highlife.survival, [2, 3]);
+    }
+
+    #[test]
+    fn normalises_counts() {
+        // Prefixes in either case, surrounding space, and counts in any order
+        // with repeats all come out the same
+        let rule = Ruleset::parse(" b33/s32 ").unwrap();
+        assert_eq!(rule.birth, [3]);
+        assert_eq!(rule.survival, [2, 3]);
+        assert_eq!(rule.to_string(), "B3/S23");
+        assert_eq!(rule, Ruleset::parse(DEFAULT_RULE).unwrap());
+    }
+
+    #[test]
+    fn applies_the_counts() {
+        let rule = Ruleset::parse("B36/S23").unwrap();
+        assert!(rule.born(1, 2) && rule.born(4, 2) && !rule.born(2, 2));
+        assert!(rule.survives(2, 0) && rule.survives(0, 3) && !rule.survives(4, 0));
+    }
+
+    #[test]
+    fn rejects_malformed_rules() {
+        // Missing prefixes
+        assert!(Ruleset::parse("3/S23").is_err());
+        assert!(Ruleset::parse("B3/23").is_err());
+        // Nine neighbours is more than there are
+        assert!(Ruleset::parse("B39/S23").is_err());
+        // Missing '/'
+        assert!(Ruleset::parse("B3S23").is_err());
+        // Repeated parts
+        assert!(Ruleset::parse("B3/B3").is_err());
+        assert!(Ruleset::parse("B3/S23/S23").is_err());
+        // Stray characters, and nothing at all
+        assert!(Ruleset::parse("B3x/S23").is_err());
+        assert!(Ruleset::parse("").is_err());
+    }
+
+    #[test]
+    fn parses_generations_states() {
+        let rule = Ruleset::parse("B2/S345/C4").unwrap();
+        assert_eq!(rule.states, 4);
+        assert_eq!(rule.to_string(), "B2/S345/C4");
+        assert_eq!(Ruleset::parse("B2/S345/c4").unwrap(), rule);
+        // Two states is an ordinary rule, and written as one
+        assert_eq!(Ruleset::parse("B3/S23/C2").unwrap().to_string(), DEFAULT_RULE);
+        assert_eq!(Ruleset::parse(DEFAULT_RULE).unwrap().states, 2);
+    }
+
+    #[test]
+    fn rejects_bad_state_counts() {
+        assert!(Ruleset::parse("B2/S345/C1").is_err());
+        assert!(Ruleset::parse("B2/S345/C256").is_err());
+        assert!(Ruleset::parse("B2/S345/4").is_err());
+        assert!(Ruleset::parse("B2/S345/C").is_err());
+        assert!(Ruleset::parse("B3/S23/C3/C3").is_err());
+    }
+
+    #[test]
+    fn parses_preset_names() {
+        for &(name, notation) in PRESETS.iter() {
+            assert_eq!(Ruleset::parse(name).unwrap(), Ruleset::parse(notation).unwrap());
+        }
+        // In any case, with space around them
+        assert_eq!(Ruleset::parse(" HighLife ").unwrap().to_string(), "B36/S23");
+        assert_eq!(Ruleset::parse("DAYNIGHT").unwrap().to_string(), "B3678/S34678");
+        assert!(Ruleset::parse("lif").is_err());
+    }
+
+    #[test]
+    fn names_and_cycles_presets() {
+        assert_eq!(Ruleset::parse("B36/S23").unwrap().preset_name(), Some("highlife"));
+        assert_eq!(Ruleset::parse("B36/S23/C3").unwrap().preset_name(), None);
+        // The odds aren't part of a preset
+        let mut seeds = Ruleset::parse("B2/S").unwrap();
+        seeds.birth_prob = 0.5;
+        assert_eq!(seeds.preset_name(), Some("seeds"));
+        // Every preset leads to the next, the last back to the first, and
+        // anything else to the first
+        let mut rule = Ruleset::parse("B36/S23/C3").unwrap();
+        for _ in 0..2 {
+            for &(name, _) in PRESETS.iter() {
+                rule = rule.next_preset();
+                assert_eq!(rule.preset_name(), Some(name));
+            }
+        }
+    }
+
+    #[test]
+    fn parses_split_counts() {
+        let rule = Ruleset::parse("B1:2/S23:*").unwrap();
+        assert!(rule.birth.is_empty() && rule.survival.is_empty());
+        assert!(rule.born(1, 2) && !rule.born(2, 1));
+        assert!(rule.survives(3, 4) && !rule.survives(1, 1));
+        assert_eq!(rule.to_string(), "B1:2/S23:*");
+        // A plain half allows every split adding up to its counts, and is
+        // written back out as them
+        assert_eq!(Ruleset::parse("B3/S2:*").unwrap().to_string(), "B0:3,1:2,2:1,3:0/S2:*");
+        // Orthogonal counts allowing the same diagonal ones are grouped
+        assert_eq!(Ruleset::parse("B1:0,4:0/S*:*").unwrap().to_string(), "B14:0/S*:*");
+    }
+
+    #[test]
+    fn rejects_malformed_split_counts() {
+        assert!(Ruleset::parse("B1:5/S2:*").is_err());
+        assert!(Ruleset::parse("B1:/S2:*").is_err());
+        assert!(Ruleset::parse("B1:2:3/S2:*").is_err());
+        // Errors name the rule as it was given
+        assert_eq!(Ruleset::parse("B0:0/S2:*"),
+                   Err("rule 'B0:0/S2:*' can't give birth with no neighbours".to_string()));
+    }
+}
@@ -0,0 +1,198 @@
+// Whole sessions saved as JSON: the board with its trails, the rule and the
+// other settings that affect how it evolves, and the viewport.  Unknown
+// fields are ignored and missing ones take their defaults, so saves keep
+// loading as fields are added; SESSION_VERSION only needs bumping for
+// changes old versions can't make sense of.
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json;
+
+use map::{BoundaryMode, Cell, Map, Neighbourhood, SimKind, MAX_RADIUS};
+use rules::Ruleset;
+
+const SESSION_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct Session {
+    version: u32,
+    width: usize,
+    height: usize,
+    rule: String,
+    boundary: BoundaryMode,
+    kind: SimKind,
+    #[serde(default = "default_neighbourhood")]
+    neighbourhood: Neighbourhood,
+    #[serde(default = "default_radius")]
+    radius: usize,
+    generation: u64,
+    #[serde(default)]
+    o_x: i32,
+    #[serde(default)]
+    o_y: i32,
+    // Whatever the frontend uses to name its palette
+    #[serde(default)]
+    palette: String,
+    // One number per cell in row-major order, see pack_cell()
+    cells: Vec<u64>
+}
+
+fn default_neighbourhood() -> Neighbourhood { Neighbourhood::Moore }
+fn default_radius() -> usize { 1 }
+
+// alive_for went in above the old 32 bits, so older saves load with every
+// cell newly born
+fn pack_cell(cell: &Cell) -> u64 {
+    cell.alive as u64 | (cell.dying as u64) << 1 | (cell.color as u64) << 2
+        | (cell.linger as u64) << 8 | (cell.age as u64) << 16 | (cell.alive_for as u64) << 32
+}
+
+fn unpack_cell(n: u64) -> Cell {
+    Cell {
+        alive: n & 1 != 0,
+        dying: n & 2 != 0,
+        color: (n >> 2 & 1) as u8,
+        linger: (n >> 8) as u8,
+        age: (n >> 16) as u8,
+        alive_for: (n >> 32) as u16
+    }
+}
+
+impl Map {
+    pub fn save_session(&self, path: &Path, palette: &str) -> Result<(), String> {
+        fs::write(path, self.session_json(palette))
+            .map_err(|e| format!("couldn't save {}: {}", path.display(), e))?;
+        info!("saved generation {} to {}", self.generation, path.display());
+        Ok(())
+    }
+
+    // A map restored from a saved session, along with the palette it was
+    // saved with.  Settings that aren't part of a session, like the rewind
+    // depth, start out at their defaults.
+    pub fn load_session(path: &Path) -> Result<(Map, String), String> {
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+        let (map, palette) = Map::from_session_json(&json).map_err(|e| format!("{}: {}", path.display(), e))?;
+        info!("loaded generation {} from {}", map.generation, path.display());
+        Ok((map, palette))
+    }
+
+    fn session_json(&self, palette: &str) -> String {
+        let session = Session {
+            version: SESSION_VERSION,
+            width: self.width,
+            height: self.height,
+            rule: self.rule.to_string(),
+            boundary: self.boundary,
+            kind: self.kind,
+            neighbourhood: self.neighbourhood,
+            radius: self.radius,
+            generation: self.generation,
+            o_x: self.o_x,
+            o_y: self.o_y,
+            palette: palette.to_string(),
+            cells: self.front.iter().map(pack_cell).collect()
+        };
+        serde_json::to_string(&session).expect("sessions always serialize")
+    }
+
+    fn from_session_json(json: &str) -> Result<(Map, String), String> {
+        let session: Session = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        if session.version > SESSION_VERSION {
+            return Err(format!("saved by a newer version (session format {})", session.version));
+        }
+        // Nothing in a save is trusted to be in range, since it could have
+        // been edited by hand
+        let size = session.width.checked_mul(session.height)
+            .ok_or_else(|| format!("{}x{} board is too big", session.width, session.height))?;
+        if session.cells.len() != size {
+            return Err(format!("expected {} cells, found {}", size, session.cells.len()));
+        }
+        if session.radius == 0 || session.radius > MAX_RADIUS {
+            return Err(format!("radius {} isn't from 1 to {}", session.radius, MAX_RADIUS));
+        }
+        let rule = Ruleset::parse(&session.rule)?;
+        let cells: Vec<Cell> = session.cells.into_iter().map(unpack_cell).collect();
+        if let Some(cell) = cells.iter().find(|c| c.dying && c.age >= rule.states - 1) {
+            return Err(format!("dying cell of age {} is past the rule's {} states", cell.age, rule.states));
+        }
+        let mut map = Map::new(session.width, session.height, rule);
+        map.boundary = session.boundary;
+        map.kind = session.kind;
+        map.neighbourhood = session.neighbourhood;
+        map.radius = session.radius;
+        map.generation = session.generation;
+        map.o_x = session.o_x;
+        map.o_y = session.o_y;
+        map.front = cells;
+        Ok((map, session.palette))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_round_trip() {
+        let mut map = Map::new(40, 30, Ruleset::parse("B36/S23").unwrap());
+        map.boundary = BoundaryMode::Wrap;
+        map.kind = SimKind::Immigration;
+        map.init_noise_seeded(5);
+        for _ in 0..3 {
+            map.tick();
+        }
+        map.o_x = 4;
+        let json = map.session_json("ice");
+
+        let (mut copy, palette) = Map::from_session_json(&json).unwrap();
+        assert_eq!(palette, "ice");
+        assert_eq!((copy.width, copy.height), (40, 30));
+        assert_eq!(copy.rule, map.rule);
+        assert_eq!(copy.boundary, BoundaryMode::Wrap);
+        assert_eq!(copy.kind, SimKind::Immigration);
+        assert_eq!(copy.generation, 3);
+        assert_eq!(copy.o_x, 4);
+        assert!(copy.front.iter().zip(map.front.iter()).all(|(a, b)| pack_cell(a) == pack_cell(b)));
+        // Both carry on identically
+        map.tick();
+        copy.tick();
+        assert_eq!(copy.checksum(), map.checksum());
+    }
+
+    #[test]
+    fn tolerates_unknown_and_missing_fields() {
+        let json = r#"{"version": 1, "width": 2, "height": 1, "rule": "B3/S23",
+                       "boundary": "Dead", "kind": "Life", "generation": 7,
+                       "cells": [1, 0], "added_later": true}"#;
+        let (map, palette) = Map::from_session_json(json).unwrap();
+        assert_eq!(palette, "");
+        assert!(map.cell(0, 0).alive && !map.cell(1, 0).alive);
+        assert_eq!(map.radius, 1);
+    }
+
+    #[test]
+    fn rejects_bad_sessions() {
+        assert!(Map::from_session_json("not json").is_err());
+        let newer = r#"{"version": 99, "width": 1, "height": 1, "rule": "B3/S23",
+                        "boundary": "Dead", "kind": "Life", "generation": 0, "cells": [0]}"#;
+        assert!(Map::from_session_json(newer).is_err());
+        let short = r#"{"version": 1, "width": 2, "height": 2, "rule": "B3/S23",
+                        "boundary": "Dead", "kind": "Life", "generation": 0, "cells": [0]}"#;
+        assert!(Map::from_session_json(short).is_err());
+        // Sizes that overflow, radii that would stall every tick, and cells
+        // further into dying than the rule allows
+        let huge = r#"{"version": 1, "width": 4294967296, "height": 4294967296, "rule": "B3/S23",
+                       "boundary": "Dead", "kind": "Life", "generation": 0, "cells": [0]}"#;
+        assert!(Map::from_session_json(huge).is_err());
+        let wide = r#"{"version": 1, "width": 1, "height": 1, "rule": "B3/S23", "radius": 1000000,
+                       "boundary": "Dead", "kind": "Life", "generation": 0, "cells": [0]}"#;
+        assert!(Map::from_session_json(wide).is_err());
+        let old = r#"{"version": 1, "width": 1, "height": 1, "rule": "B2/S345/C4",
+                      "boundary": "Dead", "kind": "Life", "generation": 0, "cells": [196610]}"#;
+        assert!(Map::from_session_json(old).is_err());
+        assert!(Map::from_session_json(&old.replace("196610", "131074")).is_ok());
+    }
+}
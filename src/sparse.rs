@@ -0,0 +1,186 @@
+// A board that stores only where its live cells are, for large maps that are
+// mostly empty.  Each tick only visits the live cells and their neighbours,
+// so a few gliders on a huge map cost next to nothing, while a crowded board
+// is better off as a Map.  It follows a Map's rule, edges and neighbourhood
+// exactly, but keeps no trails and only handles plain two-state Life.
+
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+use map::{neighbour_of, BoundaryMode, Map, Neighbourhood, SimKind};
+use rules::Ruleset;
+
+pub struct SparseMap {
+    pub width: usize,
+    pub height: usize,
+    pub generation: u64,
+    rule: Ruleset,
+    boundary: BoundaryMode,
+    // Where each neighbour is relative to the cell
+    offsets: Vec<(i32, i32)>,
+    live: HashSet<(i32, i32)>,
+    // The generation before, to spot period 2 oscillators
+    prev: Option<HashSet<(i32, i32)>>,
+    period: Option<u32>
+}
+
+impl SparseMap {
+    // The live cells of `map`, carried on under its settings.  Fails for the
+    // settings only a Map can run.
+    pub fn from_map(map: &Map) -> Result<SparseMap, String> {
+        if map.kind != SimKind::Life || map.rule.states != 2 {
+            return Err("the sparse backend only runs two-state Life rules".to_string());
+        }
+        if map.margin > 0 {
+            return Err("the sparse backend can't keep a margin".to_string());
+        }
+        if map.rule.split.is_some() {
+            return Err("the sparse backend can't tell orthogonal from diagonal neighbours".to_string());
+        }
+        if map.rule.is_stochastic() {
+            return Err("the sparse backend can't run stochastic rules".to_string());
+        }
+        // The background of a B0 rule fills the whole board
+        if map.rule.birth.contains(&0) {
+            return Err("the sparse backend can't run B0 rules".to_string());
+        }
+        // Ticks count outwards from each live cell, which only gives the
+        // right totals if every cell is its neighbours' neighbour.  Reflected
+        // edges and the hex grid's staggered rows break that.
+        if map.boundary == BoundaryMode::Mirror {
+            return Err("the sparse backend can't run with mirrored edges".to_string());
+        }
+        let r = map.radius as i32;
+        let mut offsets = Vec::new();
+        for dy in -r..r + 1 {
+            for dx in -r..r + 1 {
+                let inside = match map.neighbourhood {
+                    Neighbourhood::Moore => true,
+                    Neighbourhood::VonNeumann => dx.abs() + dy.abs() <= r,
+                    Neighbourhood::Hex => return Err("the sparse backend can't run on the hex grid".to_string())
+                };
+                if inside && (dx, dy) != (0, 0) {
+                    offsets.push((dx, dy));
+                }
+            }
+        }
+        Ok(SparseMap {
+            width: map.width,
+            height: map.height,
+            generation: map.generation,
+            rule: map.rule.clone(),
+            boundary: map.boundary,
+            offsets,
+            live: map.live_iter().map(|(x, y)| (x as i32, y as i32)).collect(),
+            prev: None,
+            period: None
+        })
+    }
+
+    // Advance one generation.  Returns false if nothing changed.
+    pub fn tick(&mut self) -> bool {
+        let mut counts: HashMap<(i32, i32), u8> = HashMap::new();
+        for &(x, y) in &self.live {
+            for &(dx, dy) in &self.offsets {
+                if let Some((i, j)) = neighbour_of(self.boundary, self.width, self.height, x + dx, y + dy) {
+                    let count = counts.entry((i as i32, j as i32)).or_insert(0);
+                    *count = count.saturating_add(1);
+                }
+            }
+        }
+        // Live cells with no live neighbours never got counted
+        let lonely = if self.rule.survival.contains(&0) {
+            self.live.iter().filter(|c| !counts.contains_key(c)).cloned().collect()
+        } else {
+            Vec::new()
+        };
+        let next: HashSet<(i32, i32)> = counts.into_iter()
+            .filter(|&(cell, n)| {
+                let counts = if self.live.contains(&cell) { &self.rule.survival } else { &self.rule.birth };
+                counts.contains(&n)
+            })
+            .map(|(cell, _)| cell)
+            .chain(lonely)
+            .collect();
+        let changed = next != self.live;
+        self.period = if !changed {
+            Some(1)
+        } else if self.prev.as_ref() == Some(&next) {
+            Some(2)
+        } else {
+            None
+        };
+        self.prev = Some(mem::replace(&mut self.live, next));
+        self.generation = self.generation.wrapping_add(1);
+        changed
+    }
+
+    pub fn live_cells(&self) -> usize {
+        self.live.len()
+    }
+
+    // As Map::period
+    pub fn period(&self) -> Option<u32> {
+        self.period
+    }
+
+    // Copy the board back into `map`, which should be the one this came
+    // from.  Its trails are lost.
+    pub fn write_to(&self, map: &mut Map) {
+        map.clear();
+        for &(x, y) in &self.live {
+            map.set_alive(x as usize, y as usize, true);
+        }
+        map.generation = self.generation;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn live(map: &Map) -> Vec<(usize, usize)> {
+        map.live_iter().collect()
+    }
+
+    // Run both backends side by side and check they agree every generation
+    fn compare(mut map: Map, generations: u64) {
+        let mut sparse = SparseMap::from_map(&map).unwrap();
+        let mut copy = map.clone();
+        for _ in 0..generations {
+            assert_eq!(sparse.tick(), map.tick());
+            sparse.write_to(&mut copy);
+            assert_eq!(live(&copy), live(&map), "generation {}", map.generation);
+            assert_eq!(sparse.period(), map.period());
+        }
+        assert_eq!(sparse.generation, map.generation);
+    }
+
+    #[test]
+    fn matches_the_dense_map() {
+        for &(rule, boundary) in &[("B3/S23", BoundaryMode::Dead), ("B3/S23", BoundaryMode::Wrap),
+                                    ("B36/S23", BoundaryMode::CylinderX), ("B2/S0", BoundaryMode::Dead)] {
+            let mut map = Map::new(60, 40, Ruleset::parse(rule).unwrap());
+            map.boundary = boundary;
+            map.init_noise_seeded(7);
+            compare(map, 30);
+        }
+        let mut map = Map::new(60, 40, Ruleset::parse("B3/S23").unwrap());
+        map.neighbourhood = Neighbourhood::VonNeumann;
+        map.radius = 2;
+        map.init_random(0.3, 3);
+        compare(map, 20);
+    }
+
+    #[test]
+    fn rejects_what_it_cant_run() {
+        let mut map = Map::new(10, 10, Ruleset::parse("B3/S23").unwrap());
+        map.boundary = BoundaryMode::Mirror;
+        assert!(SparseMap::from_map(&map).is_err());
+        map.boundary = BoundaryMode::Dead;
+        map.neighbourhood = Neighbourhood::Hex;
+        assert!(SparseMap::from_map(&map).is_err());
+        assert!(SparseMap::from_map(&Map::new(10, 10, Ruleset::parse("antilife").unwrap())).is_err());
+        assert!(SparseMap::from_map(&Map::new(10, 10, Ruleset::parse("B2/S/C3").unwrap())).is_err());
+    }
+}
@@ -0,0 +1,308 @@
+// A lightweight frontend that runs in a plain terminal, for machines without
+// SDL or a display.  Each character shows two rows of cells with half
+// blocks, drawn with ANSI escapes; the terminal is put into raw mode through
+// termios so keys arrive as they're pressed.  That keeps it to libc, but
+// also to Unix: elsewhere it builds, and says it can't run.
+
+extern crate conway_rs;
+#[cfg(unix)]
+extern crate libc;
+
+use conway_rs::{time_seed, Map, Ruleset, DEFAULT_RULE, MAP_HEIGHT, MAP_WIDTH};
+
+use std::env;
+use std::io::{self, Read, Write};
+#[cfg(unix)]
+use std::mem;
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "\
+Usage: conway-term [--rule RULE] [--seed N] [--fps N]
+
+Keys: Enter run / pause, Space step, r reseed, c clear, arrows or hjkl pan,
++ / - speed, q, Escape or Ctrl+C quit";
+
+// How often the screen is redrawn and the keyboard read
+const FRAME: Duration = Duration::from_millis(40);
+const MIN_TICK_RATE: u32 = 1;
+const MAX_TICK_RATE: u32 = 60;
+// How far a key moves the view
+const PAN_STEP: i32 = 4;
+
+// The terminal in raw mode: no echo, no line buffering, no signals from
+// Ctrl+C or Ctrl+Z, and reads that return straight away.  Ctrl+C comes
+// through as a key instead, so quitting always goes through Drop, which puts
+// the terminal back how it was.
+#[cfg(unix)]
+struct RawTerminal {
+    saved: libc::termios
+}
+
+#[cfg(not(unix))]
+struct RawTerminal;
+
+#[cfg(not(unix))]
+impl RawTerminal {
+    fn enable() -> Result<RawTerminal, String> {
+        Err("conway-term needs a Unix terminal".to_string())
+    }
+
+    fn size(&self) -> (usize, usize) {
+        (80, 24)
+    }
+}
+
+#[cfg(unix)]
+impl RawTerminal {
+    fn enable() -> Result<RawTerminal, String> {
+        unsafe {
+            let mut saved: libc::termios = mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut saved) != 0 {
+                return Err("stdin is not a terminal".to_string());
+            }
+            let mut raw = saved;
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+            raw.c_cc[libc::VMIN] = 0;
+            raw.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                return Err("couldn't put the terminal into raw mode".to_string());
+            }
+            Ok(RawTerminal { saved })
+        }
+    }
+
+    // (columns, rows), or 80 x 24 if the terminal won't say
+    fn size(&self) -> (usize, usize) {
+        unsafe {
+            let mut ws: libc::winsize = mem::zeroed();
+            if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut ws) == 0 && ws.ws_col > 0 {
+                (ws.ws_col as usize, ws.ws_row as usize)
+            } else {
+                (80, 24)
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        // Show the cursor and leave the alternate screen
+        print!("\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.saved) } != 0 {
+            eprintln!("conway-term: couldn't restore the terminal; try running `reset`");
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Key {
+    Enter,
+    Space,
+    Escape,
+    Up,
+    Down,
+    Left,
+    Right,
+    // Ctrl+C, which raw mode turns into a key
+    Interrupt,
+    Char(char)
+}
+
+// The keys in a read from stdin, with arrow keys' escape sequences turned
+// back into keys
+fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let key = match bytes[i] {
+            b'\r' | b'\n' => Key::Enter,
+            b' ' => Key::Space,
+            3 => Key::Interrupt,
+            27 if bytes.get(i + 1) == Some(&b'[') && i + 2 < bytes.len() => {
+                i += 2;
+                match bytes[i] {
+                    b'A' => Key::Up,
+                    b'B' => Key::Down,
+                    b'C' => Key::Right,
+                    b'D' => Key::Left,
+                    _ => { i += 1; continue; }
+                }
+            },
+            27 => Key::Escape,
+            b => Key::Char(b as char)
+        };
+        keys.push(key);
+        i += 1;
+    }
+    keys
+}
+
+// A character showing a top and a bottom cell
+fn half_block(top: bool, bottom: bool) -> char {
+    match (top, bottom) {
+        (true, true) => '\u{2588}',
+        (true, false) => '\u{2580}',
+        (false, true) => '\u{2584}',
+        (false, false) => ' '
+    }
+}
+
+// The whole screen as one string, to be written in a single go: the view of
+// the map filling all but the last row, which holds the status line
+fn render(map: &Map, cols: usize, rows: usize, status: &str) -> String {
+    let alive = |x: i32, y: i32| x >= 0 && y >= 0 && (x as usize) < map.width && (y as usize) < map.height
+        && map.cell(x as usize, y as usize).alive;
+    let mut frame = String::from("\x1b[H");
+    for row in 0..rows.saturating_sub(1) as i32 {
+        for col in 0..cols as i32 {
+            let (x, y) = (map.o_x + col, map.o_y + 2 * row);
+            frame.push(half_block(alive(x, y), alive(x, y + 1)));
+        }
+        frame.push_str("\r\n");
+    }
+    let status: String = status.chars().take(cols).collect();
+    frame.push_str("\x1b[7m");
+    frame.push_str(&status);
+    frame.push_str("\x1b[0m\x1b[K");
+    frame
+}
+
+fn main() {
+    let mut rule = DEFAULT_RULE.to_string();
+    let mut seed = None;
+    let mut tick_rate = 10;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            },
+            "--rule" | "--seed" | "--fps" => args.next(),
+            _ => {
+                eprintln!("conway-term: unknown option {}\n\n{}", arg, USAGE);
+                process::exit(1);
+            }
+        };
+        let value = value.unwrap_or_else(|| {
+            eprintln!("conway-term: {} needs a value", arg);
+            process::exit(1);
+        });
+        let numeric = |v: &str| v.parse::<u32>().unwrap_or_else(|_| {
+            eprintln!("conway-term: {} expects a number, got {}", arg, v);
+            process::exit(1);
+        });
+        match arg.as_str() {
+            "--rule" => rule = value,
+            "--seed" => seed = Some(numeric(&value)),
+            _ => tick_rate = numeric(&value).clamp(MIN_TICK_RATE, MAX_TICK_RATE)
+        }
+    }
+    let rule = Ruleset::parse(&rule).unwrap_or_else(|e| {
+        eprintln!("conway-term: {}", e);
+        process::exit(1);
+    });
+
+    let mut map = Map::new(MAP_WIDTH, MAP_HEIGHT, rule);
+    let mut seed = seed.unwrap_or_else(time_seed);
+    map.init_noise_seeded(seed);
+
+    let terminal = match RawTerminal::enable() {
+        Ok(terminal) => terminal,
+        Err(e) => {
+            eprintln!("conway-term: {}", e);
+            process::exit(1);
+        }
+    };
+    // Switch to the alternate screen and hide the cursor
+    print!("\x1b[?1049h\x1b[?25l\x1b[2J");
+
+    let (mut cols, mut rows) = terminal.size();
+    let (mut view_w, mut view_h) = (cols as i32, 2 * (rows as i32 - 1));
+    map.center_view(view_w, view_h);
+    let mut running = false;
+    let mut message = "";
+    let mut last_tick = Instant::now();
+    let mut stdin = io::stdin();
+    let mut buf = [0u8; 64];
+    'main: loop {
+        let frame_start = Instant::now();
+        // Follow the terminal when it's resized, keeping the same middle
+        let size = terminal.size();
+        if size != (cols, rows) {
+            let (mid_x, mid_y) = (map.o_x + view_w / 2, map.o_y + view_h / 2);
+            (cols, rows) = size;
+            view_w = cols as i32;
+            view_h = 2 * (rows as i32 - 1);
+            map.center_view_on(mid_x, mid_y, view_w, view_h);
+            print!("\x1b[2J");
+        }
+        let n = stdin.read(&mut buf).unwrap_or(0);
+        for key in parse_keys(&buf[..n]) {
+            match key {
+                Key::Enter => {
+                    running = !running;
+                    message = "";
+                },
+                Key::Space if !running => {
+                    message = if map.tick() { "" } else { "Stabilized" };
+                },
+                Key::Char('r') => {
+                    seed = seed.wrapping_add(1);
+                    map.init_noise_seeded(seed);
+                    message = "";
+                },
+                Key::Char('c') => map.clear(),
+                Key::Up | Key::Char('k') => map.pan(0, -PAN_STEP, view_w, view_h),
+                Key::Down | Key::Char('j') => map.pan(0, PAN_STEP, view_w, view_h),
+                Key::Left | Key::Char('h') => map.pan(-PAN_STEP, 0, view_w, view_h),
+                Key::Right | Key::Char('l') => map.pan(PAN_STEP, 0, view_w, view_h),
+                Key::Char('+') | Key::Char('=') => tick_rate = (tick_rate + 1).min(MAX_TICK_RATE),
+                Key::Char('-') => tick_rate = (tick_rate - 1).max(MIN_TICK_RATE),
+                Key::Char('q') | Key::Escape | Key::Interrupt => break 'main,
+                _ => {}
+            }
+        }
+        if running && last_tick.elapsed() >= Duration::from_secs(1) / tick_rate {
+            last_tick = Instant::now();
+            if !map.tick() {
+                running = false;
+                message = "Stabilized";
+            }
+        }
+        let status = format!(" gen {}  pop {}  {} tps  {}  {} ", map.generation, map.live_cells(), tick_rate,
+                             if running { "running" } else { "paused" }, message);
+        print!("{}", render(&map, cols, rows, &status));
+        let _ = io::stdout().flush();
+        if let Some(rest) = FRAME.checked_sub(frame_start.elapsed()) {
+            thread::sleep(rest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arrow_keys_are_decoded() {
+        assert_eq!(parse_keys(b"\x1b[Ak\x1b[D\r"), [Key::Up, Key::Char('k'), Key::Left, Key::Enter]);
+        assert_eq!(parse_keys(b"\x1b"), [Key::Escape]);
+        assert_eq!(parse_keys(b"\x03"), [Key::Interrupt]);
+    }
+
+    #[test]
+    fn two_rows_per_character() {
+        let mut map = Map::new(4, 4, Ruleset::parse(DEFAULT_RULE).unwrap());
+        map.set_alive(0, 0, true);
+        map.set_alive(1, 1, true);
+        map.set_alive(2, 0, true);
+        map.set_alive(2, 1, true);
+        let frame = render(&map, 4, 3, "status");
+        assert!(frame.starts_with("\x1b[H\u{2580}\u{2584}\u{2588} \r\n    \r\n"));
+    }
+}
@@ -0,0 +1,71 @@
+// A browser frontend: the simulation exported to JavaScript through
+// wasm-bindgen, for a page to tick and draw onto a <canvas>.  Build it with
+//
+//     cargo build --lib --release --target wasm32-unknown-unknown \
+//         --no-default-features --features wasm
+//
+// and run the result through `wasm-bindgen --target web`.  There's no clock
+// to seed from in the browser, so seeds come from the page.
+
+use wasm_bindgen::prelude::*;
+
+use map::Map;
+use rules::Ruleset;
+
+#[wasm_bindgen]
+pub struct Life {
+    map: Map
+}
+
+#[wasm_bindgen]
+impl Life {
+    // An empty board running `rule`, which may be any rule or preset name
+    // the native --rule accepts
+    #[wasm_bindgen(constructor)]
+    pub fn new(width: usize, height: usize, rule: &str) -> Result<Life, JsValue> {
+        let rule = Ruleset::parse(rule).map_err(|e| JsValue::from_str(&e))?;
+        Ok(Life { map: Map::new(width, height, rule) })
+    }
+
+    pub fn width(&self) -> usize {
+        self.map.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.map.height
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.map.generation
+    }
+
+    // Advance a generation.  False once the board has stopped changing.
+    pub fn tick(&mut self) -> bool {
+        self.map.tick()
+    }
+
+    // Flip the cell at (x, y), e.g. under a click.  Outside the board does
+    // nothing.
+    pub fn toggle(&mut self, x: i32, y: i32) {
+        self.map.toggle(x, y);
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    pub fn randomize(&mut self, density: f32, seed: u32) {
+        self.map.init_random(density, seed);
+    }
+
+    // The cells to draw, as x, y pairs one after the other.  Under B0 rules
+    // these are the cells that differ from background().
+    pub fn live_cells(&self) -> Vec<u32> {
+        self.map.live_iter().flat_map(|(x, y)| vec![x as u32, y as u32]).collect()
+    }
+
+    // Whether every cell not in live_cells() is alive
+    pub fn background(&self) -> bool {
+        self.map.background()
+    }
+}